@@ -3,6 +3,7 @@ use dashi::{Context, ContextInfo, ShaderType};
 use furikake::recipe::RecipeBook;
 use furikake::reservations::ReservedTiming;
 use furikake::reservations::bindless_camera::ReservedBindlessCamera;
+use furikake::reservations::bindless_lights::ReservedBindlessLights;
 use furikake::reservations::bindless_materials::ReservedBindlessMaterials;
 use furikake::reservations::bindless_textures::ReservedBindlessTextures;
 use furikake::reservations::bindless_transformations::ReservedBindlessTransformations;
@@ -45,8 +46,13 @@ fn compile_shader() -> bento::CompilationResult {
             Texture textures[];
         } meshi_bindless_textures;
 
+        struct Transformation {
+            mat4 model;
+            mat4 normal_matrix;
+            mat4 previous_model;
+        };
         layout(set = 3, binding = 0) buffer Transformations {
-            mat4 transforms[];
+            Transformation transforms[];
         } meshi_bindless_transformations;
 
         struct Material {
@@ -61,14 +67,36 @@ fn compile_shader() -> bento::CompilationResult {
             Material materials[];
         } meshi_bindless_materials;
 
+        struct Light {
+            vec3 position;
+            uint kind;
+            vec3 direction;
+            float range;
+            vec3 color;
+            float intensity;
+            float inner_cone_angle;
+            float outer_cone_angle;
+            uint shadow_map_index;
+            uint _padding0;
+            vec2 area_extent;
+            vec2 _padding1;
+        };
+        layout(set = 5, binding = 0) buffer Lights {
+            uint light_count;
+            Light lights[];
+        } meshi_bindless_lights;
+
         void main() {
             float time_mix = meshi_timing.frame_time_ms * 0.001;
             vec3 camera_dir = normalize(meshi_bindless_camera.cameras[0].rotation.xyz);
             uint texture_id = meshi_bindless_textures.textures[0].id;
-            mat4 model = meshi_bindless_transformations.transforms[0];
+            mat4 model = meshi_bindless_transformations.transforms[0].model;
             uint material_tex = meshi_bindless_materials.materials[0].base_color_texture_id;
+            float light = meshi_bindless_lights.light_count > 0
+                ? meshi_bindless_lights.lights[0].intensity
+                : 0.0;
 
-            if (time_mix + camera_dir.x + float(texture_id + material_tex) + model[0][0] > -1.0) {
+            if (time_mix + camera_dir.x + float(texture_id + material_tex) + model[0][0] + light > -1.0) {
                 // reference everything so the compiler keeps all reserved bindings
             }
         }
@@ -121,7 +149,7 @@ fn main() {
     let mut camera_handle = None;
     state
         .reserved_mut::<ReservedBindlessCamera, _>("meshi_bindless_camera", |cameras| {
-            let handle = cameras.add_camera();
+            let handle = cameras.add_camera().expect("add camera");
             let camera = cameras.camera_mut(handle);
             *camera =
                 furikake::types::Camera::new(Vec3::new(1.0, 2.0, 3.0), Quat::from_rotation_y(1.2));
@@ -132,7 +160,7 @@ fn main() {
     let mut texture_handle = None;
     state
         .reserved_mut::<ReservedBindlessTextures, _>("meshi_bindless_textures", |textures| {
-            let handle = textures.add_texture();
+            let handle = textures.add_texture().expect("add texture");
             let texture = textures.texture_mut(handle);
             texture.id = 7;
             texture.width = 2048;
@@ -147,7 +175,7 @@ fn main() {
         .reserved_mut::<ReservedBindlessTransformations, _>(
             "meshi_bindless_transformations",
             |transforms| {
-                let handle = transforms.add_transformation();
+                let handle = transforms.add_transformation().expect("add transformation");
                 transforms.transformation_mut(handle).transform =
                     Mat4::from_translation(Vec3::new(4.0, 5.0, 6.0));
                 transform_handle = Some(handle);
@@ -158,7 +186,7 @@ fn main() {
     let mut material_handle = None;
     state
         .reserved_mut::<ReservedBindlessMaterials, _>("meshi_bindless_materials", |materials| {
-            let handle = materials.add_material();
+            let handle = materials.add_material().expect("add material");
             let material = materials.material_mut(handle);
             material.base_color_texture_id = 2;
             material.normal_texture_id = 3;
@@ -169,6 +197,22 @@ fn main() {
         })
         .expect("mutate materials");
 
+    let mut light_handle = None;
+    state
+        .reserved_mut::<ReservedBindlessLights, _>("meshi_bindless_lights", |lights| {
+            light_handle = Some(
+                lights
+                    .add_light(furikake::types::Light::point(
+                        Vec3::new(0.0, 3.0, 0.0),
+                        Vec3::ONE,
+                        8.0,
+                        10.0,
+                    ))
+                    .expect("add light"),
+            );
+        })
+        .expect("mutate lights");
+
     // Drive timing forward and flush any host-side changes.
     state.update().expect("refresh reserved state");
 
@@ -202,6 +246,10 @@ fn main() {
     let texture_handle = texture_handle.expect("texture handle");
     let transform_handle = transform_handle.expect("transform handle");
     let material_handle = material_handle.expect("material handle");
+    let light_handle = light_handle.expect("light handle");
+    let lights = state
+        .reserved::<ReservedBindlessLights>("meshi_bindless_lights")
+        .expect("light reservation");
 
     println!(
         "Camera[{}] position: {:?}",
@@ -237,6 +285,15 @@ fn main() {
         materials.material(material_handle).emissive_texture_id,
     );
 
+    let light = lights.light(light_handle).expect("live light");
+    println!(
+        "Light[{}] of {} -> intensity {} range {}",
+        lights.light_index(light_handle).unwrap_or_default(),
+        lights.light_count(),
+        light.intensity,
+        light.range,
+    );
+
     // Cook the bindless resources after we've populated data to mirror real usage.
     for mut recipe in bg_recipes.drain(..) {
         let _group = recipe.cook(&mut ctx).expect("cook bind group");
@@ -2,13 +2,32 @@ use std::{error::Error, fmt};
 
 use dashi::GPUError;
 
+use crate::{recipe::BindingConflict, resolver::LayoutMismatch};
+
+#[cfg(feature = "gltf")]
+type GltfError = gltf::Error;
+/// Without the importer `GltfImport` is never constructed.
+#[cfg(not(feature = "gltf"))]
+type GltfError = std::convert::Infallible;
+
 #[derive(Debug)]
 pub enum FurikakeError {
     BufferMapFailed { source: GPUError },
     BufferUnmapFailed { source: GPUError },
+    UploadFailed { source: GPUError },
     MissingReservedBinding { name: String },
+    UnresolvedBinding { name: String },
+    MisspelledReservedBinding { name: String, suggestion: String },
     ReservedItemTypeMismatch { name: String },
+    ReservedNameTaken { name: String },
     ResolverReflection { source: String },
+    ReservationFull { name: String },
+    StaleHandle { name: String },
+    ParentCycle { name: String },
+    PushConstantMismatch { mismatch: LayoutMismatch },
+    LayoutMismatch { mismatches: Vec<LayoutMismatch> },
+    BindingConflict { conflicts: Vec<BindingConflict> },
+    GltfImport { source: GltfError },
 }
 
 impl FurikakeError {
@@ -19,6 +38,10 @@ impl FurikakeError {
     pub fn buffer_unmap_failed<E: Into<GPUError>>(err: E) -> Self {
         Self::BufferUnmapFailed { source: err.into() }
     }
+
+    pub fn upload_failed<E: Into<GPUError>>(err: E) -> Self {
+        Self::UploadFailed { source: err.into() }
+    }
 }
 
 impl fmt::Display for FurikakeError {
@@ -30,15 +53,58 @@ impl fmt::Display for FurikakeError {
             FurikakeError::BufferUnmapFailed { source } => {
                 write!(f, "failed to unmap buffer: {}", source)
             }
+            FurikakeError::UploadFailed { source } => {
+                write!(f, "failed to upload buffer data: {}", source)
+            }
             FurikakeError::MissingReservedBinding { name } => {
                 write!(f, "reserved binding `{}` not found", name)
             }
+            FurikakeError::UnresolvedBinding { name } => {
+                write!(f, "no resource was provided for `{}`", name)
+            }
+            FurikakeError::MisspelledReservedBinding { name, suggestion } => write!(
+                f,
+                "reserved binding `{}` not found; did you mean `{}`?",
+                name, suggestion
+            ),
             FurikakeError::ReservedItemTypeMismatch { name } => {
                 write!(f, "reserved binding `{}` had the wrong type", name)
             }
+            FurikakeError::ReservedNameTaken { name } => {
+                write!(f, "reserved binding `{}` is already registered", name)
+            }
             FurikakeError::ResolverReflection { source } => {
                 write!(f, "failed to reflect resolver bindings: {}", source)
             }
+            FurikakeError::ReservationFull { name } => {
+                write!(f, "reserved binding `{}` is out of space", name)
+            }
+            FurikakeError::StaleHandle { name } => {
+                write!(f, "handle into `{}` is stale or out of range", name)
+            }
+            FurikakeError::ParentCycle { name } => {
+                write!(f, "reparenting in `{}` would create a cycle", name)
+            }
+            FurikakeError::PushConstantMismatch { mismatch } => {
+                write!(f, "push constant does not match the shader: {}", mismatch)
+            }
+            FurikakeError::LayoutMismatch { mismatches } => {
+                write!(f, "shader layout does not match reserved data:")?;
+                for mismatch in mismatches {
+                    write!(f, "\n  {}", mismatch)?;
+                }
+                Ok(())
+            }
+            FurikakeError::BindingConflict { conflicts } => {
+                write!(f, "shaders declare conflicting bindings:")?;
+                for conflict in conflicts {
+                    write!(f, "\n  {}", conflict)?;
+                }
+                Ok(())
+            }
+            FurikakeError::GltfImport { source } => {
+                write!(f, "failed to import glTF: {}", source)
+            }
         }
     }
 }
@@ -47,9 +113,17 @@ impl Error for FurikakeError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         match self {
             FurikakeError::BufferMapFailed { source }
-            | FurikakeError::BufferUnmapFailed { source } => Some(source),
+            | FurikakeError::BufferUnmapFailed { source }
+            | FurikakeError::UploadFailed { source } => Some(source),
+            FurikakeError::GltfImport { source } => Some(source),
             FurikakeError::ResolverReflection { .. }
+            | FurikakeError::ReservationFull { .. }
+            | FurikakeError::StaleHandle { .. }
+            | FurikakeError::PushConstantMismatch { .. }
+            | FurikakeError::LayoutMismatch { .. }
+            | FurikakeError::BindingConflict { .. }
             | FurikakeError::MissingReservedBinding { .. }
+            | FurikakeError::MisspelledReservedBinding { .. }
             | FurikakeError::ReservedItemTypeMismatch { .. } => None,
         }
     }
@@ -63,6 +137,13 @@ impl From<GPUError> for FurikakeError {
     }
 }
 
+#[cfg(feature = "gltf")]
+impl From<gltf::Error> for FurikakeError {
+    fn from(value: gltf::Error) -> Self {
+        FurikakeError::GltfImport { source: value }
+    }
+}
+
 impl From<String> for FurikakeError {
     fn from(value: String) -> Self {
         FurikakeError::ResolverReflection { source: value }
@@ -0,0 +1,329 @@
+use std::path::Path;
+
+use dashi::Handle;
+use glam::Mat4;
+
+use crate::{
+    BindlessState,
+    error::FurikakeError,
+    reservations::{
+        ReservedItem, bindless_materials::ReservedBindlessMaterials,
+        bindless_textures::ReservedBindlessTextures,
+        bindless_transformations::ReservedBindlessTransformations,
+    },
+    types::{Material, Texture, Transformation},
+};
+
+/// Texture id written into material slots the glTF material leaves empty.
+pub const NO_TEXTURE: u32 = u32::MAX;
+
+/// Mapping from glTF indices to the handles they were imported into.
+pub struct GltfImport {
+    /// Indexed by glTF texture index.
+    pub textures: Vec<Handle<Texture>>,
+    /// Indexed by glTF material index.
+    pub materials: Vec<Handle<Material>>,
+    /// Indexed by glTF node index. Each slot holds the node's world transform.
+    pub nodes: Vec<Handle<Transformation>>,
+    /// Decoded images indexed by glTF image index. The importer does not
+    /// upload them: `Texture::id` holds the glTF image index, an index into
+    /// this list rather than a GPU image, until the caller uploads the image
+    /// and overwrites the id.
+    pub images: Vec<::gltf::image::Data>,
+}
+
+/// Imports a `.gltf` or `.glb` file, resolving embedded and external buffers.
+pub fn import<P: AsRef<Path>>(
+    path: P,
+    textures: &mut ReservedBindlessTextures,
+    materials: &mut ReservedBindlessMaterials,
+    transformations: &mut ReservedBindlessTransformations,
+) -> Result<GltfImport, FurikakeError> {
+    let (document, _buffers, images) = ::gltf::import(path)?;
+    populate(&document, images, textures, materials, transformations)
+}
+
+/// Imports a glTF document already loaded into memory. External buffers are
+/// not available in this mode, so the document must be self-contained.
+pub fn import_slice(
+    bytes: &[u8],
+    textures: &mut ReservedBindlessTextures,
+    materials: &mut ReservedBindlessMaterials,
+    transformations: &mut ReservedBindlessTransformations,
+) -> Result<GltfImport, FurikakeError> {
+    let (document, _buffers, images) = ::gltf::import_slice(bytes)?;
+    populate(&document, images, textures, materials, transformations)
+}
+
+/// Imports a `.gltf` or `.glb` file into the reservations owned by `state`.
+pub fn import_into_state<P: AsRef<Path>>(
+    state: &mut BindlessState,
+    path: P,
+) -> Result<GltfImport, FurikakeError> {
+    let (document, _buffers, images) = ::gltf::import(path)?;
+
+    let keys = [
+        "meshi_bindless_textures",
+        "meshi_bindless_materials",
+        "meshi_bindless_transformations",
+    ];
+    let [textures, materials, transformations] = state.reserved.get_disjoint_mut(keys);
+    populate(
+        &document,
+        images,
+        downcast(keys[0], textures)?,
+        downcast(keys[1], materials)?,
+        downcast(keys[2], transformations)?,
+    )
+}
+
+fn downcast<'a, T: 'static>(
+    key: &str,
+    item: Option<&'a mut Box<dyn ReservedItem>>,
+) -> Result<&'a mut T, FurikakeError> {
+    item.ok_or(FurikakeError::MissingReservedBinding {
+        name: key.to_string(),
+    })?
+    .as_any_mut()
+    .downcast_mut::<T>()
+    .ok_or(FurikakeError::ReservedItemTypeMismatch {
+        name: key.to_string(),
+    })
+}
+
+fn populate(
+    document: &::gltf::Document,
+    images: Vec<::gltf::image::Data>,
+    textures: &mut ReservedBindlessTextures,
+    materials: &mut ReservedBindlessMaterials,
+    transformations: &mut ReservedBindlessTransformations,
+) -> Result<GltfImport, FurikakeError> {
+    let mut imported = GltfImport {
+        textures: Vec::new(),
+        materials: Vec::new(),
+        nodes: Vec::new(),
+        images,
+    };
+
+    let result = import_textures(document, &imported.images, textures, &mut imported.textures)
+        .and_then(|()| {
+            import_materials(
+                document,
+                &imported.textures,
+                materials,
+                &mut imported.materials,
+            )
+        })
+        .and_then(|()| import_nodes(document, transformations, &mut imported.nodes));
+
+    // A failed import hands nothing back, so release what it allocated.
+    if let Err(err) = result {
+        imported
+            .textures
+            .into_iter()
+            .for_each(|handle| textures.remove_texture(handle));
+        imported
+            .materials
+            .into_iter()
+            .for_each(|handle| materials.remove_material(handle));
+        imported
+            .nodes
+            .into_iter()
+            .for_each(|handle| transformations.remove_transformation(handle));
+        return Err(err);
+    }
+
+    Ok(imported)
+}
+
+fn import_textures(
+    document: &::gltf::Document,
+    images: &[::gltf::image::Data],
+    textures: &mut ReservedBindlessTextures,
+    handles: &mut Vec<Handle<Texture>>,
+) -> Result<(), FurikakeError> {
+    document.textures().try_for_each(|texture| {
+        let image = texture.source().index();
+        let handle = textures.add_texture()?;
+        let record = textures.texture_mut(handle);
+        // Importer-local: indexes `GltfImport::images`, not a GPU image.
+        record.id = image as u32;
+        record.mip_levels = 1;
+        if let Some(data) = images.get(image) {
+            record.width = data.width;
+            record.height = data.height;
+        }
+        handles.push(handle);
+        Ok(())
+    })
+}
+
+fn import_materials(
+    document: &::gltf::Document,
+    texture_handles: &[Handle<Texture>],
+    materials: &mut ReservedBindlessMaterials,
+    handles: &mut Vec<Handle<Material>>,
+) -> Result<(), FurikakeError> {
+    let texture_id = |index: Option<usize>| {
+        index
+            .and_then(|i| texture_handles.get(i))
+            .map(|handle| u32::from(handle.slot))
+            .unwrap_or(NO_TEXTURE)
+    };
+
+    document.materials().try_for_each(|material| {
+        let pbr = material.pbr_metallic_roughness();
+        let handle = materials.add_material()?;
+        *materials.material_mut(handle) = Material {
+            base_color_texture_id: texture_id(
+                pbr.base_color_texture().map(|info| info.texture().index()),
+            ),
+            normal_texture_id: texture_id(
+                material.normal_texture().map(|info| info.texture().index()),
+            ),
+            metallic_roughness_texture_id: texture_id(
+                pbr.metallic_roughness_texture()
+                    .map(|info| info.texture().index()),
+            ),
+            occlusion_texture_id: texture_id(
+                material
+                    .occlusion_texture()
+                    .map(|info| info.texture().index()),
+            ),
+            emissive_texture_id: texture_id(
+                material
+                    .emissive_texture()
+                    .map(|info| info.texture().index()),
+            ),
+            _padding: 0,
+        };
+        handles.push(handle);
+        Ok(())
+    })
+}
+
+fn import_nodes(
+    document: &::gltf::Document,
+    transformations: &mut ReservedBindlessTransformations,
+    handles: &mut Vec<Handle<Transformation>>,
+) -> Result<(), FurikakeError> {
+    let mut world: Vec<Option<Mat4>> = vec![None; document.nodes().len()];
+    for scene in document.scenes() {
+        for root in scene.nodes() {
+            accumulate_world(&root, Mat4::IDENTITY, &mut world);
+        }
+    }
+
+    document.nodes().try_for_each(|node| {
+        let handle = transformations.add_transformation()?;
+        transformations.transformation_mut(handle).transform =
+            world[node.index()].unwrap_or_else(|| local_matrix(&node));
+        handles.push(handle);
+        Ok(())
+    })
+}
+
+fn accumulate_world(node: &::gltf::Node, parent: Mat4, world: &mut [Option<Mat4>]) {
+    let transform = parent * local_matrix(node);
+    world[node.index()] = Some(transform);
+    for child in node.children() {
+        accumulate_world(&child, transform, world);
+    }
+}
+
+fn local_matrix(node: &::gltf::Node) -> Mat4 {
+    Mat4::from_cols_array_2d(&node.transform().matrix())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reservations::MAX_SLOTS;
+    use dashi::{Context, ContextInfo};
+    use glam::Vec3;
+
+    const SCENE: &str = r#"{
+        "asset": { "version": "2.0" },
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": [
+            { "translation": [1.0, 0.0, 0.0], "children": [1] },
+            { "translation": [0.0, 2.0, 0.0] }
+        ],
+        "images": [{
+            "uri": "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mP8z8BQDwAEhQGAhKmMIQAAAABJRU5ErkJggg=="
+        }],
+        "textures": [{ "source": 0 }],
+        "materials": [{
+            "pbrMetallicRoughness": { "baseColorTexture": { "index": 0 } }
+        }]
+    }"#;
+
+    #[test]
+    fn imports_materials_textures_and_nodes() {
+        let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
+        let mut textures = ReservedBindlessTextures::new(&mut ctx);
+        let mut materials = ReservedBindlessMaterials::new(&mut ctx);
+        let mut transformations = ReservedBindlessTransformations::new(&mut ctx);
+
+        let imported = import_slice(
+            SCENE.as_bytes(),
+            &mut textures,
+            &mut materials,
+            &mut transformations,
+        )
+        .expect("import gltf");
+
+        assert_eq!(imported.textures.len(), 1);
+        let texture = textures.texture(imported.textures[0]);
+        assert_eq!(texture.id, 0);
+        assert_eq!((texture.width, texture.height), (1, 1));
+
+        assert_eq!(imported.materials.len(), 1);
+        let material = materials.material(imported.materials[0]);
+        assert_eq!(
+            material.base_color_texture_id,
+            u32::from(imported.textures[0].slot)
+        );
+        assert_eq!(material.normal_texture_id, NO_TEXTURE);
+
+        assert_eq!(imported.nodes.len(), 2);
+        let child = transformations.transformation(imported.nodes[1]);
+        assert_eq!(child.transform.w_axis.truncate(), Vec3::new(1.0, 2.0, 0.0));
+    }
+
+    #[test]
+    fn releases_slots_when_the_import_fails() {
+        let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
+        let mut textures = ReservedBindlessTextures::new(&mut ctx);
+        let mut materials = ReservedBindlessMaterials::new(&mut ctx);
+        let mut transformations = ReservedBindlessTransformations::new(&mut ctx);
+
+        let next_texture = textures.add_texture().expect("free slot");
+        textures.remove_texture(next_texture);
+        let next_material = materials.add_material().expect("free slot");
+        materials.remove_material(next_material);
+        // Leave room for the first node only.
+        for _ in 0..MAX_SLOTS - 1 {
+            transformations.add_transformation().expect("free slot");
+        }
+
+        let result = import_slice(
+            SCENE.as_bytes(),
+            &mut textures,
+            &mut materials,
+            &mut transformations,
+        );
+        assert!(matches!(result, Err(FurikakeError::ReservationFull { .. })));
+
+        assert_eq!(
+            textures.add_texture().expect("free slot").slot,
+            next_texture.slot
+        );
+        assert_eq!(
+            materials.add_material().expect("free slot").slot,
+            next_material.slot
+        );
+        assert!(transformations.add_transformation().is_ok());
+    }
+}
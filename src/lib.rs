@@ -1,29 +1,62 @@
 pub mod error;
+pub mod importers;
 pub mod recipe;
 pub mod reservations;
 pub mod resolver;
+pub mod scene;
 pub mod types;
 
-use dashi::{BindGroupVariableType, Context};
+use dashi::{BindGroupVariableType, Context, Handle};
 use error::FurikakeError;
 use reservations::{
-    ReservedItem, ReservedTiming, bindless_camera::ReservedBindlessCamera,
-    bindless_materials::ReservedBindlessMaterials, bindless_textures::ReservedBindlessTextures,
+    BindlessMemory, ReservedItem, ReservedTiming,
+    bindless_bounds::ReservedBindlessBounds,
+    bindless_camera::ReservedBindlessCamera,
+    bindless_geometry::ReservedBindlessGeometry,
+    bindless_instances::ReservedBindlessInstances,
+    bindless_lights::ReservedBindlessLights,
+    bindless_materials::ReservedBindlessMaterials,
+    bindless_skeletons::ReservedBindlessSkeletons,
+    bindless_textures::ReservedBindlessTextures,
     bindless_transformations::ReservedBindlessTransformations,
+    counters::ReservedCounters,
+    debug_draw::ReservedDebugDraw,
+    debug_printf::ReservedDebugPrintf,
+    environment::ReservedEnvironment,
+    input::ReservedInput,
+    light_clusters::{ClusterGrid, ReservedLightClusters},
+    particles::ReservedParticles,
+    push_constants::ReservedPushConstants,
+    random::{ReservedRandom, generate_blue_noise},
+    viewport::ReservedViewport,
 };
 use std::{collections::HashMap, ptr::NonNull};
+use types::{Camera, Material, Texture};
 
 pub use resolver::*;
 
-pub struct ReservedMetadata {
-    pub name: &'static str,
+#[derive(Clone, Debug)]
+pub struct ReservedMetadata<'a> {
+    pub name: &'a str,
     pub kind: BindGroupVariableType,
 }
 
 pub trait GPUState {
     fn reserved_names() -> &'static [&'static str];
-    fn reserved_metadata() -> &'static [ReservedMetadata];
+    fn reserved_metadata() -> &'static [ReservedMetadata<'static>];
     fn binding(&self, key: &str) -> Result<&dyn ReservedItem, FurikakeError>;
+
+    /// Every reservation this state binds: `reserved_metadata` plus any
+    /// registered at runtime. The resolver checks shaders against this list.
+    fn metadata(&self) -> Vec<ReservedMetadata<'_>> {
+        Self::reserved_metadata().to_vec()
+    }
+
+    /// Names of reserved push-constant blocks. These are looked up through
+    /// `binding` like any other reservation.
+    fn reserved_push_constants() -> &'static [&'static str] {
+        &[]
+    }
 }
 
 pub struct DefaultState {
@@ -33,7 +66,10 @@ pub struct DefaultState {
 
 pub struct BindlessState {
     ctx: NonNull<Context>,
+    memory: BindlessMemory,
     reserved: HashMap<String, Box<dyn ReservedItem>>,
+    /// Names added through `register_materials`.
+    registered: Vec<String>,
 }
 
 #[cfg(test)]
@@ -78,6 +114,182 @@ mod tests {
             .expect("unmap timing buffer after mutation");
     }
 
+    #[test]
+    fn ties_random_seed_to_timing_frames() {
+        let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
+        let mut state = BindlessState::new(&mut ctx);
+
+        state.update().expect("first frame");
+        state.update().expect("second frame");
+
+        let random = state
+            .reserved::<ReservedRandom>("meshi_random")
+            .expect("random reference");
+        assert_eq!(random.data().frame_index, 1);
+        assert_eq!(
+            state
+                .reserved::<ReservedTiming>("meshi_timing")
+                .expect("timing reference")
+                .frame_index(),
+            2
+        );
+
+        let texture = state.register_blue_noise(16, 5).expect("register noise");
+        let random = state
+            .reserved::<ReservedRandom>("meshi_random")
+            .expect("random reference");
+        assert_eq!(random.data().blue_noise_texture_id, texture.slot as u32);
+        assert_eq!(
+            random.blue_noise().map(|noise| noise.values.len()),
+            Some(256)
+        );
+        let textures = state
+            .reserved::<ReservedBindlessTextures>("meshi_bindless_textures")
+            .expect("textures reference");
+        assert_eq!(textures.texture(texture).id, 5);
+    }
+
+    #[test]
+    fn refreshes_bounds_of_moved_objects() {
+        let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
+        let mut state = BindlessState::new(&mut ctx);
+
+        let mut object = Handle::new(0, 0);
+        state
+            .reserved_mut::<ReservedBindlessTransformations, _>(
+                "meshi_bindless_transformations",
+                |transformations| object = transformations.add_transformation().expect("free slot"),
+            )
+            .expect("add transformation");
+        state
+            .reserved_mut::<ReservedBindlessBounds, _>("meshi_bindless_bounds", |bounds| {
+                bounds.set_bounds(
+                    object,
+                    types::Bounds::from_aabb(glam::Vec3::ZERO, glam::Vec3::ONE),
+                )
+            })
+            .expect("set bounds");
+
+        state.update().expect("first update");
+        assert_eq!(state.refresh_bounds().expect("refresh"), 0);
+
+        state
+            .reserved_mut::<ReservedBindlessTransformations, _>(
+                "meshi_bindless_transformations",
+                |transformations| {
+                    transformations.transformation_mut(object).transform =
+                        glam::Mat4::from_translation(glam::Vec3::X)
+                },
+            )
+            .expect("move object");
+        assert_eq!(state.refresh_bounds().expect("refresh"), 1);
+
+        let bounds = state
+            .reserved::<ReservedBindlessBounds>("meshi_bindless_bounds")
+            .expect("bounds reference");
+        assert_eq!(bounds.bounds(object).world_min, glam::Vec3::X);
+    }
+
+    #[test]
+    fn registers_custom_material_layouts() {
+        #[repr(C)]
+        #[derive(Clone, Copy, Default)]
+        struct TerrainMaterial {
+            splat_texture_id: u32,
+            tiling: f32,
+        }
+
+        let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
+        let mut state = BindlessState::new(&mut ctx);
+        state
+            .register_materials::<TerrainMaterial>("meshi_terrain_materials")
+            .expect("register terrain materials");
+
+        let mut terrain = None;
+
+        state
+            .reserved_mut::<ReservedBindlessMaterials<TerrainMaterial>, _>(
+                "meshi_terrain_materials",
+                |materials| {
+                    let handle = materials.add_material().expect("free slot");
+                    let material = materials.material_mut(handle);
+                    material.splat_texture_id = 3;
+                    material.tiling = 4.0;
+                    terrain = Some(handle);
+                },
+            )
+            .expect("mutate terrain materials");
+
+        let terrain = terrain.expect("terrain handle");
+        let materials = state
+            .reserved::<ReservedBindlessMaterials<TerrainMaterial>>("meshi_terrain_materials")
+            .expect("terrain materials");
+        assert_eq!(materials.material(terrain).splat_texture_id, 3);
+        assert_eq!(materials.material(terrain).tiling, 4.0);
+
+        let binding = state
+            .binding("meshi_terrain_materials")
+            .expect("terrain binding");
+        assert_eq!(binding.name(), "meshi_terrain_materials");
+
+        assert!(matches!(
+            state.register_materials::<TerrainMaterial>("meshi_terrain_materials"),
+            Err(FurikakeError::ReservedNameTaken { .. })
+        ));
+        assert!(matches!(
+            state.register_materials::<TerrainMaterial>("meshi_timing"),
+            Err(FurikakeError::ReservedNameTaken { .. })
+        ));
+        state
+            .reserved::<ReservedTiming>("meshi_timing")
+            .expect("timing left in place");
+
+        let metadata = state.metadata();
+        let registered: Vec<_> = metadata
+            .iter()
+            .filter(|meta| meta.name == "meshi_terrain_materials")
+            .collect();
+        assert_eq!(registered.len(), 1);
+        assert_eq!(metadata.len(), BINDLESS_METADATA.len() + 1);
+    }
+
+    #[test]
+    fn assigns_state_lights_to_clusters() {
+        let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
+        let mut state = BindlessState::new(&mut ctx);
+
+        let mut camera = None;
+        state
+            .reserved_mut::<ReservedBindlessCamera, _>("meshi_bindless_camera", |cameras| {
+                camera = Some(cameras.add_camera().expect("free slot"));
+            })
+            .expect("add camera");
+        state
+            .reserved_mut::<ReservedBindlessLights, _>("meshi_bindless_lights", |lights| {
+                lights
+                    .add_light(types::Light::directional(
+                        glam::Vec3::NEG_Y,
+                        glam::Vec3::ONE,
+                        1.0,
+                    ))
+                    .expect("free slot");
+            })
+            .expect("add light");
+
+        state
+            .assign_light_clusters(camera.expect("camera handle"))
+            .expect("assign clusters");
+        state.update().expect("upload clusters");
+
+        let clusters = state
+            .reserved::<ReservedLightClusters>("meshi_light_clusters")
+            .expect("cluster reservation");
+        assert_eq!(
+            clusters.assignment().light_indices.len(),
+            clusters.grid().cluster_count()
+        );
+    }
+
     #[test]
     fn errors_on_type_mismatch() {
         let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
@@ -100,24 +312,64 @@ mod tests {
 ///////////////////////////////////////////////////////////
 ///
 
-const DEFAULT_STATE_NAMES: [&str; 1] = ["meshi_timing"];
-const DEFAULT_METADATA: [ReservedMetadata; 1] = [ReservedMetadata {
-    name: "meshi_timing",
-    kind: BindGroupVariableType::Uniform,
-}];
+const PUSH_CONSTANT_NAMES: [&str; 1] = ["meshi_draw"];
+
+const DEFAULT_STATE_NAMES: [&str; 7] = [
+    "meshi_timing",
+    "meshi_environment",
+    "meshi_viewport",
+    "meshi_input",
+    "meshi_debug_draw",
+    "meshi_debug_printf",
+    "meshi_counters",
+];
+const DEFAULT_METADATA: [ReservedMetadata; 7] = [
+    ReservedMetadata {
+        name: "meshi_timing",
+        kind: BindGroupVariableType::Uniform,
+    },
+    ReservedMetadata {
+        name: "meshi_environment",
+        kind: BindGroupVariableType::Uniform,
+    },
+    ReservedMetadata {
+        name: "meshi_viewport",
+        kind: BindGroupVariableType::Uniform,
+    },
+    ReservedMetadata {
+        name: "meshi_input",
+        kind: BindGroupVariableType::Uniform,
+    },
+    ReservedMetadata {
+        name: "meshi_debug_draw",
+        kind: BindGroupVariableType::Storage,
+    },
+    ReservedMetadata {
+        name: "meshi_debug_printf",
+        kind: BindGroupVariableType::Storage,
+    },
+    ReservedMetadata {
+        name: "meshi_counters",
+        kind: BindGroupVariableType::Storage,
+    },
+];
 
 impl GPUState for DefaultState {
     fn reserved_names() -> &'static [&'static str] {
         DEFAULT_STATE_NAMES.as_slice()
     }
 
-    fn reserved_metadata() -> &'static [ReservedMetadata] {
+    fn reserved_metadata() -> &'static [ReservedMetadata<'static>] {
         DEFAULT_METADATA.as_slice()
     }
 
     fn binding(&self, key: &str) -> Result<&dyn ReservedItem, FurikakeError> {
         <DefaultState>::binding(self, key)
     }
+
+    fn reserved_push_constants() -> &'static [&'static str] {
+        PUSH_CONSTANT_NAMES.as_slice()
+    }
 }
 
 impl DefaultState {
@@ -126,6 +378,22 @@ impl DefaultState {
 
         let names = DEFAULT_STATE_NAMES;
         reserved.insert(names[0].to_string(), Box::new(ReservedTiming::new(ctx)));
+        reserved.insert(
+            names[1].to_string(),
+            Box::new(ReservedEnvironment::new(ctx)),
+        );
+        reserved.insert(names[2].to_string(), Box::new(ReservedViewport::new(ctx)));
+        reserved.insert(names[3].to_string(), Box::new(ReservedInput::new(ctx)));
+        reserved.insert(names[4].to_string(), Box::new(ReservedDebugDraw::new(ctx)));
+        reserved.insert(
+            names[5].to_string(),
+            Box::new(ReservedDebugPrintf::new(ctx)),
+        );
+        reserved.insert(names[6].to_string(), Box::new(ReservedCounters::new(ctx)));
+        reserved.insert(
+            PUSH_CONSTANT_NAMES[0].to_string(),
+            Box::new(ReservedPushConstants::draw()),
+        );
 
         Self {
             reserved,
@@ -195,14 +463,36 @@ impl DefaultState {
 ///////////////////////////////////////////////////////////
 ///
 
-const BINDLESS_STATE_NAMES: [&str; 5] = [
+const BINDLESS_STATE_NAMES: [&str; 27] = [
     "meshi_timing",
     "meshi_bindless_camera",
     "meshi_bindless_textures",
     "meshi_bindless_transformations",
     "meshi_bindless_materials",
+    "meshi_bindless_lights",
+    "meshi_light_clusters",
+    "meshi_light_cluster_indices",
+    "meshi_bindless_geometry",
+    "meshi_bindless_vertices",
+    "meshi_bindless_indices",
+    "meshi_bindless_instances",
+    "meshi_indirect_draws",
+    "meshi_bindless_skeletons",
+    "meshi_bindless_joints",
+    "meshi_environment",
+    "meshi_viewport",
+    "meshi_input",
+    "meshi_random",
+    "meshi_debug_draw",
+    "meshi_debug_printf",
+    "meshi_counters",
+    "meshi_particles",
+    "meshi_particle_lists",
+    "meshi_particle_emitters",
+    "meshi_particle_dispatch",
+    "meshi_bindless_bounds",
 ];
-const BINDLESS_METADATA: [ReservedMetadata; 5] = [
+const BINDLESS_METADATA: [ReservedMetadata; 27] = [
     ReservedMetadata {
         name: "meshi_timing",
         kind: BindGroupVariableType::Uniform,
@@ -223,6 +513,94 @@ const BINDLESS_METADATA: [ReservedMetadata; 5] = [
         name: "meshi_bindless_materials",
         kind: BindGroupVariableType::Storage,
     },
+    ReservedMetadata {
+        name: "meshi_bindless_lights",
+        kind: BindGroupVariableType::Storage,
+    },
+    ReservedMetadata {
+        name: "meshi_light_clusters",
+        kind: BindGroupVariableType::Storage,
+    },
+    ReservedMetadata {
+        name: "meshi_light_cluster_indices",
+        kind: BindGroupVariableType::Storage,
+    },
+    ReservedMetadata {
+        name: "meshi_bindless_geometry",
+        kind: BindGroupVariableType::Storage,
+    },
+    ReservedMetadata {
+        name: "meshi_bindless_vertices",
+        kind: BindGroupVariableType::Storage,
+    },
+    ReservedMetadata {
+        name: "meshi_bindless_indices",
+        kind: BindGroupVariableType::Storage,
+    },
+    ReservedMetadata {
+        name: "meshi_bindless_instances",
+        kind: BindGroupVariableType::Storage,
+    },
+    ReservedMetadata {
+        name: "meshi_indirect_draws",
+        kind: BindGroupVariableType::Storage,
+    },
+    ReservedMetadata {
+        name: "meshi_bindless_skeletons",
+        kind: BindGroupVariableType::Storage,
+    },
+    ReservedMetadata {
+        name: "meshi_bindless_joints",
+        kind: BindGroupVariableType::Storage,
+    },
+    ReservedMetadata {
+        name: "meshi_environment",
+        kind: BindGroupVariableType::Uniform,
+    },
+    ReservedMetadata {
+        name: "meshi_viewport",
+        kind: BindGroupVariableType::Uniform,
+    },
+    ReservedMetadata {
+        name: "meshi_input",
+        kind: BindGroupVariableType::Uniform,
+    },
+    ReservedMetadata {
+        name: "meshi_random",
+        kind: BindGroupVariableType::Uniform,
+    },
+    ReservedMetadata {
+        name: "meshi_debug_draw",
+        kind: BindGroupVariableType::Storage,
+    },
+    ReservedMetadata {
+        name: "meshi_debug_printf",
+        kind: BindGroupVariableType::Storage,
+    },
+    ReservedMetadata {
+        name: "meshi_counters",
+        kind: BindGroupVariableType::Storage,
+    },
+    ReservedMetadata {
+        name: "meshi_particles",
+        kind: BindGroupVariableType::Storage,
+    },
+    ReservedMetadata {
+        name: "meshi_particle_lists",
+        kind: BindGroupVariableType::Storage,
+    },
+    ReservedMetadata {
+        name: "meshi_particle_emitters",
+        kind: BindGroupVariableType::Storage,
+    },
+    ReservedMetadata {
+        name: "meshi_particle_dispatch",
+        kind: BindGroupVariableType::Storage,
+    },
+    ReservedMetadata {
+        name: "meshi_bindless_bounds",
+        kind: BindGroupVariableType::Storage,
+    },
 ];
 
 impl GPUState for BindlessState {
@@ -230,44 +608,161 @@ impl GPUState for BindlessState {
         BINDLESS_STATE_NAMES.as_slice()
     }
 
-    fn reserved_metadata() -> &'static [ReservedMetadata] {
+    fn reserved_metadata() -> &'static [ReservedMetadata<'static>] {
         BINDLESS_METADATA.as_slice()
     }
 
+    fn metadata(&self) -> Vec<ReservedMetadata<'_>> {
+        let registered = self.registered.iter().map(|name| ReservedMetadata {
+            name,
+            kind: BindGroupVariableType::Storage,
+        });
+        BINDLESS_METADATA
+            .iter()
+            .cloned()
+            .chain(registered)
+            .collect()
+    }
+
     fn binding(&self, key: &str) -> Result<&dyn ReservedItem, FurikakeError> {
         <BindlessState>::binding(self, key)
     }
+
+    fn reserved_push_constants() -> &'static [&'static str] {
+        PUSH_CONSTANT_NAMES.as_slice()
+    }
 }
 
 impl BindlessState {
     pub fn new(ctx: &mut Context) -> Self {
+        Self::with_memory(ctx, BindlessMemory::HostMapped)
+    }
+
+    /// Creates the bindless reservations with every pool backed by `memory`.
+    pub fn with_memory(ctx: &mut Context, memory: BindlessMemory) -> Self {
         let mut reserved: HashMap<String, Box<dyn ReservedItem>> = HashMap::new();
 
         let names = BINDLESS_STATE_NAMES;
         reserved.insert(names[0].to_string(), Box::new(ReservedTiming::new(ctx)));
         reserved.insert(
             names[1].to_string(),
-            Box::new(ReservedBindlessCamera::new(ctx)),
+            Box::new(ReservedBindlessCamera::with_memory(ctx, memory)),
         );
         reserved.insert(
             names[2].to_string(),
-            Box::new(ReservedBindlessTextures::new(ctx)),
+            Box::new(ReservedBindlessTextures::with_memory(ctx, memory)),
         );
         reserved.insert(
             names[3].to_string(),
-            Box::new(ReservedBindlessTransformations::new(ctx)),
+            Box::new(ReservedBindlessTransformations::with_memory(ctx, memory)),
+        );
+        let mut materials = <ReservedBindlessMaterials>::with_memory(ctx, names[4], memory);
+        materials.set_layout(Some(Material::shader_layout()));
+        reserved.insert(names[4].to_string(), Box::new(materials));
+        reserved.insert(
+            names[5].to_string(),
+            Box::new(ReservedBindlessLights::new(ctx)),
+        );
+
+        let clusters = ReservedLightClusters::new(ctx, ClusterGrid::default());
+        reserved.insert(names[7].to_string(), Box::new(clusters.index_reservation()));
+        reserved.insert(names[6].to_string(), Box::new(clusters));
+
+        let geometry = ReservedBindlessGeometry::new(ctx);
+        reserved.insert(
+            names[9].to_string(),
+            Box::new(geometry.vertex_reservation()),
+        );
+        reserved.insert(
+            names[10].to_string(),
+            Box::new(geometry.index_reservation()),
+        );
+        reserved.insert(names[8].to_string(), Box::new(geometry));
+
+        let instances = ReservedBindlessInstances::new(ctx);
+        reserved.insert(
+            names[12].to_string(),
+            Box::new(instances.indirect_reservation()),
+        );
+        reserved.insert(names[11].to_string(), Box::new(instances));
+
+        let skeletons = ReservedBindlessSkeletons::new(ctx);
+        reserved.insert(
+            names[14].to_string(),
+            Box::new(skeletons.joint_reservation()),
+        );
+        reserved.insert(names[13].to_string(), Box::new(skeletons));
+        reserved.insert(
+            names[15].to_string(),
+            Box::new(ReservedEnvironment::new(ctx)),
+        );
+        reserved.insert(names[16].to_string(), Box::new(ReservedViewport::new(ctx)));
+        reserved.insert(names[17].to_string(), Box::new(ReservedInput::new(ctx)));
+        reserved.insert(names[18].to_string(), Box::new(ReservedRandom::new(ctx)));
+        reserved.insert(names[19].to_string(), Box::new(ReservedDebugDraw::new(ctx)));
+        reserved.insert(
+            names[20].to_string(),
+            Box::new(ReservedDebugPrintf::new(ctx)),
+        );
+        reserved.insert(names[21].to_string(), Box::new(ReservedCounters::new(ctx)));
+
+        let particles = ReservedParticles::new(ctx);
+        reserved.insert(
+            names[23].to_string(),
+            Box::new(particles.list_reservation()),
+        );
+        reserved.insert(
+            names[24].to_string(),
+            Box::new(particles.emitter_reservation()),
+        );
+        reserved.insert(
+            names[25].to_string(),
+            Box::new(particles.dispatch_reservation()),
+        );
+        reserved.insert(names[22].to_string(), Box::new(particles));
+        reserved.insert(
+            names[26].to_string(),
+            Box::new(ReservedBindlessBounds::with_memory(ctx, memory)),
         );
         reserved.insert(
-            names[4].to_string(),
-            Box::new(ReservedBindlessMaterials::new(ctx)),
+            PUSH_CONSTANT_NAMES[0].to_string(),
+            Box::new(ReservedPushConstants::draw()),
         );
 
         Self {
             reserved,
+            memory,
             ctx: NonNull::from_ref(ctx),
+            registered: Vec::new(),
         }
     }
 
+    /// Registers an additional materials reservation with a project-specific
+    /// layout, exposed to shaders as `name`. Names already taken by a built-in
+    /// or earlier registration are rejected rather than replaced.
+    pub fn register_materials<M: Copy + Default + 'static>(
+        &mut self,
+        name: &str,
+    ) -> Result<(), FurikakeError> {
+        if self.reserved.contains_key(name) {
+            return Err(FurikakeError::ReservedNameTaken {
+                name: name.to_string(),
+            });
+        }
+
+        self.registered.push(name.to_string());
+        let ctx: &mut Context = unsafe { self.ctx.as_mut() };
+        self.reserved.insert(
+            name.to_string(),
+            Box::new(ReservedBindlessMaterials::<M>::with_memory(
+                ctx,
+                name,
+                self.memory,
+            )),
+        );
+        Ok(())
+    }
+
     pub fn binding(&self, key: &str) -> Result<&dyn ReservedItem, FurikakeError> {
         if let Some(b) = self.reserved.get(key) {
             return Ok(b.as_ref());
@@ -279,6 +774,15 @@ impl BindlessState {
     }
 
     pub fn update(&mut self) -> Result<(), FurikakeError> {
+        self.refresh_bounds()?;
+
+        let frame_index = self
+            .reserved::<ReservedTiming>("meshi_timing")?
+            .frame_index();
+        self.reserved_mut::<ReservedRandom, _>("meshi_random", |random| {
+            random.set_frame_index(frame_index)
+        })?;
+
         let ctx: &mut Context = unsafe { self.ctx.as_mut() };
         for iter in &mut self.reserved {
             iter.1.update(ctx)?;
@@ -286,6 +790,87 @@ impl BindlessState {
         Ok(())
     }
 
+    /// Recomputes world bounds in `meshi_bindless_bounds` for objects whose
+    /// transformation changed. Called from `update()`.
+    pub fn refresh_bounds(&mut self) -> Result<usize, FurikakeError> {
+        let transforms: Vec<_> = {
+            let bounds = self.reserved::<ReservedBindlessBounds>("meshi_bindless_bounds")?;
+            let transformations =
+                self.reserved::<ReservedBindlessTransformations>("meshi_bindless_transformations")?;
+            bounds
+                .tracked()
+                .map(|handle| (handle, transformations.transformation(handle).transform))
+                .collect()
+        };
+
+        let mut refreshed = 0;
+        self.reserved_mut::<ReservedBindlessBounds, _>("meshi_bindless_bounds", |bounds| {
+            refreshed = transforms
+                .iter()
+                .filter(|(handle, transform)| bounds.refresh(*handle, transform))
+                .count();
+        })?;
+        Ok(refreshed)
+    }
+
+    /// Generates a `size` x `size` blue-noise tile, registers it in
+    /// `meshi_bindless_textures` as `image_id` and exposes its slot through
+    /// `meshi_random`. Upload `ReservedRandom::blue_noise()` as an R8 UNORM
+    /// image under that id.
+    pub fn register_blue_noise(
+        &mut self,
+        size: u32,
+        image_id: u32,
+    ) -> Result<Handle<Texture>, FurikakeError> {
+        let noise = generate_blue_noise(size, image_id);
+
+        let mut texture = Ok(Handle::new(0, 0));
+        self.reserved_mut::<ReservedBindlessTextures, _>("meshi_bindless_textures", |textures| {
+            texture = textures.add_texture().inspect(|handle| {
+                let record = textures.texture_mut(*handle);
+                record.id = image_id;
+                record.width = size;
+                record.height = size;
+                record.mip_levels = 1;
+            });
+        })?;
+        let texture = texture?;
+        self.reserved_mut::<ReservedRandom, _>("meshi_random", |random| {
+            random.set_blue_noise(noise, texture)
+        })?;
+
+        Ok(texture)
+    }
+
+    /// Rebuilds `meshi_bindless_instances` and `meshi_indirect_draws` from the
+    /// current instances and meshes. The result is uploaded on the next `update()`.
+    pub fn build_draws(&mut self) -> Result<(), FurikakeError> {
+        let meshes = self
+            .reserved::<ReservedBindlessGeometry>("meshi_bindless_geometry")?
+            .meshes()
+            .to_vec();
+
+        self.reserved_mut::<ReservedBindlessInstances, _>("meshi_bindless_instances", |instances| {
+            instances.build_draws(&meshes)
+        })
+    }
+
+    /// Assigns the current lights to `meshi_light_clusters` as seen from
+    /// `camera`. The result is uploaded on the next `update()`.
+    pub fn assign_light_clusters(&mut self, camera: Handle<Camera>) -> Result<(), FurikakeError> {
+        let camera = *self
+            .reserved::<ReservedBindlessCamera>("meshi_bindless_camera")?
+            .camera(camera);
+        let lights = self
+            .reserved::<ReservedBindlessLights>("meshi_bindless_lights")?
+            .lights()
+            .to_vec();
+
+        self.reserved_mut::<ReservedLightClusters, _>("meshi_light_clusters", |clusters| {
+            clusters.assign(&camera, &lights)
+        })
+    }
+
     pub fn reserved_mut<T: 'static, F: FnOnce(&mut T)>(
         &mut self,
         key: &str,
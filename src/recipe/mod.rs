@@ -3,10 +3,103 @@ use dashi::builders::{BindGroupLayoutBuilder, BindTableLayoutBuilder};
 use dashi::{
     BindGroup, BindGroupInfo, BindGroupLayout, BindGroupVariable, BindTable, BindTableInfo,
     BindTableLayout, BindingInfo, Context, Handle, IndexedBindingInfo, IndexedResource, ShaderInfo,
+    ShaderResource, ShaderType,
 };
 use std::collections::HashMap;
 
-use crate::{GPUState, error::FurikakeError, reservations::ReservedBinding, resolver::Resolver};
+use crate::{
+    GPUState,
+    error::FurikakeError,
+    reservations::ReservedBinding,
+    resolver::{RESERVED_PREFIX, Resolver},
+};
+
+/// Resources a [`UserBindings`] provider can supply. Push constants are
+/// recorded per draw and have no place here.
+pub enum UserBinding<'a> {
+    Binding(BindingInfo),
+    BindlessBinding(IndexedBindingInfo<'a>),
+}
+
+impl<'a> From<UserBinding<'a>> for ReservedBinding<'a> {
+    fn from(value: UserBinding<'a>) -> Self {
+        match value {
+            UserBinding::Binding(info) => ReservedBinding::Binding(info),
+            UserBinding::BindlessBinding(info) => ReservedBinding::BindlessBinding(info),
+        }
+    }
+}
+
+/// Puts a user resource at the binding the shader declares it at.
+fn place_user_binding<'a>(
+    binding: UserBinding<'a>,
+    var: &bento::ShaderVariable,
+) -> ReservedBinding<'a> {
+    match binding {
+        UserBinding::Binding(info) => ReservedBinding::Binding(BindingInfo {
+            binding: var.kind.binding,
+            ..info
+        }),
+        binding => binding.into(),
+    }
+}
+
+/// Supplies resources for shader variables that are not reservations, such
+/// as G-buffer textures or application-owned buffers.
+pub trait UserBindings {
+    /// Resource for `var`, or `None` to leave the slot unresolved so it can be
+    /// filled with [`BindGroupRecipe::set_binding`] or
+    /// [`BindTableRecipe::set_resources`] before cooking.
+    fn binding(&self, var: &bento::ShaderVariable) -> Option<UserBinding<'_>>;
+}
+
+/// One shader's declaration of a variable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BindingSite {
+    /// Shader name when bento reported one, otherwise its index and stage.
+    pub shader: String,
+    pub name: String,
+    pub set: u32,
+    pub binding: u32,
+}
+
+impl std::fmt::Display for BindingSite {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "`{}` at set {} binding {} in {}",
+            self.name, self.set, self.binding, self.shader
+        )
+    }
+}
+
+/// Declarations that cannot share one pipeline layout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BindingConflict {
+    /// Two different names claim the same set and binding.
+    SharedSlot {
+        first: BindingSite,
+        second: BindingSite,
+    },
+    /// One name is declared at different sets or bindings.
+    MovedName {
+        first: BindingSite,
+        second: BindingSite,
+    },
+}
+
+impl std::fmt::Display for BindingConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BindingConflict::SharedSlot { first, second } => {
+                write!(f, "{} shares its slot with {}", second, first)
+            }
+            BindingConflict::MovedName { first, second } => {
+                write!(f, "{} was declared as {}", second, first)
+            }
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct BindingRecipe {
@@ -38,12 +131,34 @@ pub struct RecipeBook {
 }
 
 impl BindGroupRecipe {
+    /// Variables still waiting for a resource.
+    pub fn unresolved(&self) -> impl Iterator<Item = &bento::ShaderVariable> {
+        self.bindings
+            .iter()
+            .filter(|recipe| recipe.binding.is_none())
+            .map(|recipe| &recipe.var)
+    }
+
+    /// Provides the resource for the variable `name`. Returns `false` when the
+    /// recipe has no such variable.
+    pub fn set_binding(&mut self, name: &str, resource: ShaderResource) -> bool {
+        let Some(recipe) = self.bindings.iter_mut().find(|b| b.var.name == name) else {
+            return false;
+        };
+
+        recipe.binding = Some(BindingInfo {
+            resource,
+            binding: recipe.var.kind.binding,
+        });
+        true
+    }
+
     pub fn cook(&mut self, ctx: &mut Context) -> Result<Handle<BindGroup>, FurikakeError> {
         let mut bindings: Vec<BindingInfo> = Vec::with_capacity(self.bindings.len());
 
         for recipe in &mut self.bindings {
             bindings.push(recipe.binding.take().ok_or_else(|| {
-                FurikakeError::MissingReservedBinding {
+                FurikakeError::UnresolvedBinding {
                     name: recipe.var.name.clone(),
                 }
             })?);
@@ -62,13 +177,32 @@ impl BindGroupRecipe {
 }
 
 impl BindTableRecipe {
+    /// Variables still waiting for resources.
+    pub fn unresolved(&self) -> impl Iterator<Item = &bento::ShaderVariable> {
+        self.bindings
+            .iter()
+            .filter(|recipe| recipe.bindings.is_none())
+            .map(|recipe| &recipe.var)
+    }
+
+    /// Provides the indexed resources for the variable `name`. Returns `false`
+    /// when the recipe has no such variable.
+    pub fn set_resources(&mut self, name: &str, resources: Vec<IndexedResource>) -> bool {
+        let Some(recipe) = self.bindings.iter_mut().find(|b| b.var.name == name) else {
+            return false;
+        };
+
+        recipe.bindings = Some(resources);
+        true
+    }
+
     pub fn cook(&mut self, ctx: &mut Context) -> Result<Handle<BindTable>, FurikakeError> {
         let mut owned_resources: Vec<Vec<IndexedResource>> =
             Vec::with_capacity(self.bindings.len());
 
         for recipe in &mut self.bindings {
             owned_resources.push(recipe.bindings.take().ok_or_else(|| {
-                FurikakeError::MissingReservedBinding {
+                FurikakeError::UnresolvedBinding {
                     name: recipe.var.name.clone(),
                 }
             })?);
@@ -94,36 +228,70 @@ impl BindTableRecipe {
     }
 }
 impl RecipeBook {
+    /// Builds recipes for `shaders`. Variables that are not reservations are
+    /// left unresolved for the caller to fill in: descriptor arrays in bind
+    /// tables, everything else in bind groups.
     pub fn new<T: GPUState>(
         ctx: &mut Context,
         state: &T,
         shaders: &[CompilationResult],
     ) -> Result<Self, FurikakeError> {
-        let mut group_layout_vars: HashMap<u32, Vec<(dashi::ShaderType, Vec<BindGroupVariable>)>> =
+        Self::build(ctx, state, None, shaders)
+    }
+
+    /// Like [`RecipeBook::new`], asking `user` for the resources of variables
+    /// that are not reservations.
+    pub fn with_user_bindings<T: GPUState>(
+        ctx: &mut Context,
+        state: &T,
+        user: &dyn UserBindings,
+        shaders: &[CompilationResult],
+    ) -> Result<Self, FurikakeError> {
+        Self::build(ctx, state, Some(user), shaders)
+    }
+
+    fn build<T: GPUState>(
+        ctx: &mut Context,
+        state: &T,
+        user: Option<&dyn UserBindings>,
+        shaders: &[CompilationResult],
+    ) -> Result<Self, FurikakeError> {
+        let conflicts = find_conflicts(shaders);
+        if !conflicts.is_empty() {
+            return Err(FurikakeError::BindingConflict { conflicts });
+        }
+
+        let mut group_layout_vars: HashMap<u32, Vec<(ShaderType, Vec<BindGroupVariable>)>> =
             HashMap::new();
-        let mut table_layout_vars: HashMap<u32, Vec<(dashi::ShaderType, Vec<BindGroupVariable>)>> =
+        let mut table_layout_vars: HashMap<u32, Vec<(ShaderType, Vec<BindGroupVariable>)>> =
             HashMap::new();
         let mut group_recipes: HashMap<u32, HashMap<String, BindingRecipe>> = HashMap::new();
         let mut table_recipes: HashMap<u32, HashMap<String, IndexedBindingRecipe>> = HashMap::new();
 
         for shader in shaders {
-            Resolver::new(state, shader)?;
+            let resolver = Resolver::new(state, shader)?;
 
             for var in &shader.variables {
-                let reserved = state.binding(&var.name)?.binding();
-                match reserved {
-                    ReservedBinding::Binding(info) => {
-                        let shader_vars = group_layout_vars.entry(var.set).or_default();
-
-                        if let Some((_stage, vars)) = shader_vars
-                            .iter_mut()
-                            .find(|(stage, _)| *stage == shader.stage)
-                        {
-                            vars.push(var.kind.clone());
-                        } else {
-                            shader_vars.push((shader.stage, vec![var.kind.clone()]));
-                        }
-
+                let binding = match state.binding(&var.name) {
+                    Ok(item) => Some(item.binding()),
+                    // Names with the reserved prefix must match a reservation.
+                    Err(err) if var.name.starts_with(RESERVED_PREFIX) => {
+                        return Err(match resolver.suggestion(&var.name) {
+                            Some(suggestion) => FurikakeError::MisspelledReservedBinding {
+                                name: var.name.clone(),
+                                suggestion: suggestion.to_string(),
+                            },
+                            None => err,
+                        });
+                    }
+                    Err(_) => user
+                        .and_then(|user| user.binding(var))
+                        .map(|binding| place_user_binding(binding, var)),
+                };
+
+                match binding {
+                    Some(ReservedBinding::Binding(info)) => {
+                        add_stage_var(&mut group_layout_vars, shader.stage, var);
                         group_recipes
                             .entry(var.set)
                             .or_default()
@@ -133,17 +301,8 @@ impl RecipeBook {
                                 var: var.clone(),
                             });
                     }
-                    ReservedBinding::BindlessBinding(binding) => {
-                        let shader_vars = table_layout_vars.entry(var.set).or_default();
-
-                        if let Some((_stage, vars)) = shader_vars
-                            .iter_mut()
-                            .find(|(stage, _)| *stage == shader.stage)
-                        {
-                            vars.push(var.kind.clone());
-                        } else {
-                            shader_vars.push((shader.stage, vec![var.kind.clone()]));
-                        }
+                    Some(ReservedBinding::BindlessBinding(binding)) => {
+                        add_stage_var(&mut table_layout_vars, shader.stage, var);
 
                         let resources = binding
                             .resources
@@ -163,6 +322,32 @@ impl RecipeBook {
                                 var: var.clone(),
                             });
                     }
+                    // Push constants are recorded per draw, not bound in a set.
+                    Some(ReservedBinding::PushConstant(_)) => {}
+                    // A user binding nobody provided; filled in before cooking.
+                    // Descriptor arrays go to a bind table like reservations do.
+                    None if var.kind.count > 1 => {
+                        add_stage_var(&mut table_layout_vars, shader.stage, var);
+                        table_recipes
+                            .entry(var.set)
+                            .or_default()
+                            .entry(var.name.clone())
+                            .or_insert_with(|| IndexedBindingRecipe {
+                                bindings: None,
+                                var: var.clone(),
+                            });
+                    }
+                    None => {
+                        add_stage_var(&mut group_layout_vars, shader.stage, var);
+                        group_recipes
+                            .entry(var.set)
+                            .or_default()
+                            .entry(var.name.clone())
+                            .or_insert_with(|| BindingRecipe {
+                                binding: None,
+                                var: var.clone(),
+                            });
+                    }
                 }
             }
         }
@@ -239,6 +424,66 @@ impl RecipeBook {
     }
 }
 
+/// Compares every declaration against the first one seen for its slot and
+/// for its name, so later shaders are reported against earlier ones.
+fn find_conflicts(shaders: &[CompilationResult]) -> Vec<BindingConflict> {
+    let mut by_slot: HashMap<(u32, u32), BindingSite> = HashMap::new();
+    let mut by_name: HashMap<String, BindingSite> = HashMap::new();
+    let mut conflicts = Vec::new();
+
+    for (index, shader) in shaders.iter().enumerate() {
+        let label = match &shader.name {
+            Some(name) => format!("`{}` ({:?})", name, shader.stage),
+            None => format!("shader {} ({:?})", index, shader.stage),
+        };
+
+        for var in &shader.variables {
+            let site = BindingSite {
+                shader: label.clone(),
+                name: var.name.clone(),
+                set: var.set,
+                binding: var.kind.binding,
+            };
+
+            let first = by_slot
+                .entry((site.set, site.binding))
+                .or_insert_with(|| site.clone());
+            if first.name != site.name {
+                conflicts.push(BindingConflict::SharedSlot {
+                    first: first.clone(),
+                    second: site.clone(),
+                });
+            }
+
+            let first = by_name
+                .entry(site.name.clone())
+                .or_insert_with(|| site.clone());
+            if (first.set, first.binding) != (site.set, site.binding) {
+                conflicts.push(BindingConflict::MovedName {
+                    first: first.clone(),
+                    second: site,
+                });
+            }
+        }
+    }
+
+    conflicts
+}
+
+/// Records `var` in the layout variables of its set for `stage`.
+fn add_stage_var(
+    layout_vars: &mut HashMap<u32, Vec<(ShaderType, Vec<BindGroupVariable>)>>,
+    stage: ShaderType,
+    var: &bento::ShaderVariable,
+) {
+    let shader_vars = layout_vars.entry(var.set).or_default();
+    if let Some((_stage, vars)) = shader_vars.iter_mut().find(|(s, _)| *s == stage) {
+        vars.push(var.kind.clone());
+    } else {
+        shader_vars.push((stage, vec![var.kind.clone()]));
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -372,7 +617,7 @@ mod tests {
             &["bindless_test"]
         }
 
-        fn reserved_metadata() -> &'static [ReservedMetadata] {
+        fn reserved_metadata() -> &'static [ReservedMetadata<'static>] {
             &[ReservedMetadata {
                 name: "bindless_test",
                 kind: BindGroupVariableType::Storage,
@@ -414,4 +659,212 @@ mod tests {
         let handle = recipe.cook(&mut ctx).expect("cook bind table");
         assert!(handle.valid());
     }
+
+    #[test]
+    fn suggests_reserved_name_for_typos() {
+        let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
+        let state = DefaultState::new(&mut ctx);
+
+        let shader = CompilationResult {
+            name: None,
+            file: None,
+            lang: bento::ShaderLang::Glsl,
+            stage: ShaderType::Vertex,
+            variables: vec![make_shader_variable(
+                "meshi_timimg",
+                0,
+                BindGroupVariableType::Uniform,
+                0,
+            )],
+            metadata: empty_metadata(),
+            spirv: Vec::new(),
+        };
+
+        match RecipeBook::new(&mut ctx, &state, &[shader]) {
+            Err(FurikakeError::MisspelledReservedBinding { name, suggestion }) => {
+                assert_eq!(name, "meshi_timimg");
+                assert_eq!(suggestion, "meshi_timing");
+            }
+            Err(other) => panic!("unexpected error {other:?}", other = other),
+            Ok(_) => panic!("misspelled binding resolved"),
+        }
+    }
+
+    fn make_storage_buffer(ctx: &mut Context) -> Handle<dashi::Buffer> {
+        ctx.make_buffer(&BufferInfo {
+            debug_name: "[FURIKAKE] Test User Buffer",
+            byte_size: 64,
+            visibility: MemoryVisibility::CpuAndGpu,
+            usage: dashi::BufferUsage::STORAGE,
+            ..Default::default()
+        })
+        .expect("make buffer")
+    }
+
+    fn mixed_shader() -> CompilationResult {
+        CompilationResult {
+            name: None,
+            file: None,
+            lang: bento::ShaderLang::Glsl,
+            stage: ShaderType::Fragment,
+            variables: vec![
+                make_shader_variable("meshi_timing", 0, BindGroupVariableType::Uniform, 0),
+                make_shader_variable("gbuffer_normals", 0, BindGroupVariableType::Storage, 1),
+            ],
+            metadata: empty_metadata(),
+            spirv: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn leaves_user_bindings_to_fill_in() {
+        let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
+        let state = DefaultState::new(&mut ctx);
+
+        let book = RecipeBook::new(&mut ctx, &state, &[mixed_shader()]).expect("build recipes");
+        let (mut bg_recipes, _) = book.recipes();
+        assert_eq!(bg_recipes.len(), 1);
+
+        let mut recipe = bg_recipes.pop().unwrap();
+        let unresolved: Vec<&str> = recipe.unresolved().map(|var| var.name.as_str()).collect();
+        assert_eq!(unresolved, ["gbuffer_normals"]);
+        assert!(matches!(
+            recipe.clone().cook(&mut ctx),
+            Err(FurikakeError::UnresolvedBinding { name }) if name == "gbuffer_normals"
+        ));
+
+        let buffer = make_storage_buffer(&mut ctx);
+        assert!(!recipe.set_binding("gbuffer_albedo", ShaderResource::StorageBuffer(buffer)));
+        assert!(recipe.set_binding("gbuffer_normals", ShaderResource::StorageBuffer(buffer)));
+        assert_eq!(recipe.unresolved().count(), 0);
+
+        let handle = recipe.cook(&mut ctx).expect("cook bind group");
+        assert!(handle.valid());
+    }
+
+    #[test]
+    fn leaves_user_descriptor_arrays_to_bind_tables() {
+        let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
+        let state = DefaultState::new(&mut ctx);
+
+        let mut shadow_maps =
+            make_shader_variable("shadow_maps", 2, BindGroupVariableType::Storage, 0);
+        shadow_maps.kind.count = 4;
+        let shader = CompilationResult {
+            variables: vec![shadow_maps],
+            ..mixed_shader()
+        };
+
+        let book = RecipeBook::new(&mut ctx, &state, &[shader]).expect("build recipes");
+        let (bg_recipes, mut bt_recipes) = book.recipes();
+        assert!(bg_recipes.is_empty());
+        assert_eq!(bt_recipes.len(), 1);
+
+        let mut recipe = bt_recipes.pop().unwrap();
+        let unresolved: Vec<&str> = recipe.unresolved().map(|var| var.name.as_str()).collect();
+        assert_eq!(unresolved, ["shadow_maps"]);
+
+        let buffer = make_storage_buffer(&mut ctx);
+        let resources = (0..4)
+            .map(|slot| IndexedResource {
+                resource: ShaderResource::StorageBuffer(buffer),
+                slot,
+            })
+            .collect();
+        assert!(recipe.set_resources("shadow_maps", resources));
+
+        let handle = recipe.cook(&mut ctx).expect("cook bind table");
+        assert!(handle.valid());
+    }
+
+    struct GBuffer {
+        normals: Handle<dashi::Buffer>,
+    }
+
+    impl UserBindings for GBuffer {
+        fn binding(&self, var: &bento::ShaderVariable) -> Option<UserBinding<'_>> {
+            (var.name == "gbuffer_normals").then(|| {
+                UserBinding::Binding(BindingInfo {
+                    resource: ShaderResource::StorageBuffer(self.normals),
+                    binding: var.kind.binding,
+                })
+            })
+        }
+    }
+
+    #[test]
+    fn asks_provider_for_user_bindings() {
+        let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
+        let state = DefaultState::new(&mut ctx);
+        let gbuffer = GBuffer {
+            normals: make_storage_buffer(&mut ctx),
+        };
+
+        let book = RecipeBook::with_user_bindings(&mut ctx, &state, &gbuffer, &[mixed_shader()])
+            .expect("build recipes");
+        let (mut bg_recipes, _) = book.recipes();
+
+        let mut recipe = bg_recipes.pop().unwrap();
+        assert_eq!(recipe.unresolved().count(), 0);
+        let handle = recipe.cook(&mut ctx).expect("cook bind group");
+        assert!(handle.valid());
+    }
+
+    #[test]
+    fn reports_binding_conflicts_across_shaders() {
+        let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
+        let state = DefaultState::new(&mut ctx);
+
+        let shader = |stage, variables| CompilationResult {
+            name: None,
+            file: None,
+            lang: bento::ShaderLang::Glsl,
+            stage,
+            variables,
+            metadata: empty_metadata(),
+            spirv: Vec::new(),
+        };
+        let vertex = shader(
+            ShaderType::Vertex,
+            vec![make_shader_variable(
+                "meshi_timing",
+                0,
+                BindGroupVariableType::Uniform,
+                0,
+            )],
+        );
+        let fragment = shader(
+            ShaderType::Fragment,
+            vec![
+                make_shader_variable("meshi_timing", 1, BindGroupVariableType::Uniform, 0),
+                make_shader_variable("gbuffer_normals", 0, BindGroupVariableType::Storage, 0),
+            ],
+        );
+
+        let conflicts = match RecipeBook::new(&mut ctx, &state, &[vertex, fragment]) {
+            Err(FurikakeError::BindingConflict { conflicts }) => conflicts,
+            Err(other) => panic!("unexpected error {other:?}", other = other),
+            Ok(_) => panic!("conflicting shaders produced recipes"),
+        };
+
+        let site = |shader: &str, name: &str, set| BindingSite {
+            shader: shader.to_string(),
+            name: name.to_string(),
+            set,
+            binding: 0,
+        };
+        assert_eq!(
+            conflicts,
+            vec![
+                BindingConflict::MovedName {
+                    first: site("shader 0 (Vertex)", "meshi_timing", 0),
+                    second: site("shader 1 (Fragment)", "meshi_timing", 1),
+                },
+                BindingConflict::SharedSlot {
+                    first: site("shader 0 (Vertex)", "meshi_timing", 0),
+                    second: site("shader 1 (Fragment)", "gbuffer_normals", 0),
+                },
+            ]
+        );
+    }
 }
@@ -0,0 +1,175 @@
+use dashi::{Context, Handle};
+use glam::Mat4;
+
+use crate::{
+    resolver::{ReservedLayout, ShaderLayout},
+    types::{Bounds, Transformation},
+};
+
+use super::{
+    ReservedBinding, ReservedItem,
+    bindless_pool::{BindlessMemory, BindlessPool, UploadStats},
+};
+
+/// Per-object bounds for GPU culling, stored at the same slot as the object's
+/// entry in `meshi_bindless_transformations`.
+///
+/// World bounds are only recomputed for slots whose transform or local bounds
+/// changed since the last refresh.
+pub struct ReservedBindlessBounds {
+    pool: BindlessPool<Bounds>,
+    // Transform the world bounds were computed from; `None` marks the slot
+    // for recomputation.
+    last_transforms: Vec<Option<Mat4>>,
+    tracked: Vec<bool>,
+}
+
+impl ReservedBindlessBounds {
+    pub fn new(ctx: &mut Context) -> Self {
+        Self::with_memory(ctx, BindlessMemory::HostMapped)
+    }
+
+    pub fn with_memory(ctx: &mut Context, memory: BindlessMemory) -> Self {
+        let pool = BindlessPool::new(ctx, "Bounds", memory);
+        Self {
+            last_transforms: vec![None; pool.len()],
+            tracked: vec![false; pool.len()],
+            pool,
+        }
+    }
+
+    /// Assigns local bounds to the object at `transformation`.
+    pub fn set_bounds(&mut self, transformation: Handle<Transformation>, bounds: Bounds) {
+        let slot = transformation.slot as usize;
+        self.pool.ensure_slot(slot);
+        self.last_transforms.resize(self.pool.len(), None);
+        self.tracked.resize(self.pool.len(), false);
+
+        *self.pool.get_mut(slot) = bounds;
+        self.last_transforms[slot] = None;
+        self.tracked[slot] = true;
+    }
+
+    pub fn remove_bounds(&mut self, transformation: Handle<Transformation>) {
+        let slot = transformation.slot as usize;
+        if self.tracked.get(slot).copied().unwrap_or(false) {
+            *self.pool.get_mut(slot) = Bounds::default();
+            self.last_transforms[slot] = None;
+            self.tracked[slot] = false;
+        }
+    }
+
+    pub fn bounds(&self, transformation: Handle<Transformation>) -> &Bounds {
+        self.pool.get(transformation.slot as usize)
+    }
+
+    /// Transformation handles that currently have bounds.
+    pub fn tracked(&self) -> impl Iterator<Item = Handle<Transformation>> + '_ {
+        self.tracked
+            .iter()
+            .enumerate()
+            .filter(|(_, tracked)| **tracked)
+            .filter_map(|(slot, _)| super::slot_handle(slot as u32))
+    }
+
+    /// Recomputes the world bounds of `transformation` if `transform` differs
+    /// from the one they were last computed with. Returns whether it did.
+    pub fn refresh(&mut self, transformation: Handle<Transformation>, transform: &Mat4) -> bool {
+        let slot = transformation.slot as usize;
+        if !self.tracked.get(slot).copied().unwrap_or(false)
+            || self.last_transforms[slot] == Some(*transform)
+        {
+            return false;
+        }
+
+        self.pool.get_mut(slot).transform(transform);
+        self.last_transforms[slot] = Some(*transform);
+        true
+    }
+
+    /// Bytes and slots uploaded by the last `update()` in device-local mode.
+    pub fn upload_stats(&self) -> UploadStats {
+        self.pool.upload_stats()
+    }
+}
+
+impl ReservedItem for ReservedBindlessBounds {
+    fn name(&self) -> String {
+        "meshi_bindless_bounds".to_string()
+    }
+
+    fn update(&mut self, ctx: &mut Context) -> Result<(), crate::error::FurikakeError> {
+        self.pool.update(ctx)
+    }
+
+    fn binding(&self) -> ReservedBinding<'_> {
+        self.pool.binding()
+    }
+
+    fn layout(&self) -> Option<ReservedLayout> {
+        Some(ReservedLayout::Elements {
+            header: 0,
+            element: Bounds::shader_layout(),
+        })
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reservations::slot_handle;
+    use dashi::ContextInfo;
+    use glam::{Quat, Vec3};
+
+    #[test]
+    fn recomputes_world_bounds_for_changed_transforms() {
+        let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
+        let mut bounds = ReservedBindlessBounds::new(&mut ctx);
+
+        let handle = slot_handle::<Transformation>(600).expect("slot in range");
+        bounds.set_bounds(
+            handle,
+            Bounds::from_aabb(Vec3::splat(-1.0), Vec3::splat(1.0)),
+        );
+        assert_eq!(bounds.tracked().collect::<Vec<_>>(), vec![handle]);
+
+        let transform = Mat4::from_scale_rotation_translation(
+            Vec3::splat(2.0),
+            Quat::from_rotation_y(std::f32::consts::FRAC_PI_4),
+            Vec3::new(10.0, 0.0, 0.0),
+        );
+        assert!(bounds.refresh(handle, &transform));
+        assert!(!bounds.refresh(handle, &transform));
+
+        let world = bounds.bounds(handle);
+        let half = 2.0 * std::f32::consts::SQRT_2;
+        assert!(
+            world
+                .world_min
+                .abs_diff_eq(Vec3::new(10.0 - half, -2.0, -half), 1e-4)
+        );
+        assert!(
+            world
+                .world_max
+                .abs_diff_eq(Vec3::new(10.0 + half, 2.0, half), 1e-4)
+        );
+        assert!(
+            world
+                .world_center
+                .abs_diff_eq(Vec3::new(10.0, 0.0, 0.0), 1e-4)
+        );
+        assert!((world.world_radius - 2.0 * 3f32.sqrt()).abs() < 1e-4);
+
+        bounds.remove_bounds(handle);
+        assert_eq!(bounds.tracked().count(), 0);
+        assert!(!bounds.refresh(handle, &Mat4::IDENTITY));
+    }
+}
@@ -0,0 +1,451 @@
+#![allow(dead_code)]
+
+use dashi::{
+    BindingInfo, Buffer, BufferInfo, BufferUsage, Context, Handle, MemoryVisibility, ShaderResource,
+};
+
+use crate::{
+    error::FurikakeError,
+    resolver::{ReservedLayout, ShaderLayout, StructLayout},
+    types::{Mesh, Vertex},
+};
+
+use super::{ReservedBinding, ReservedItem};
+
+/// Sizes of the shared geometry buffers, fixed at creation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GeometryCapacity {
+    pub vertices: u32,
+    pub indices: u32,
+    pub meshes: u32,
+}
+
+impl Default for GeometryCapacity {
+    fn default() -> Self {
+        Self {
+            vertices: 1 << 18,
+            indices: 1 << 20,
+            meshes: 4096,
+        }
+    }
+}
+
+/// Mesh descriptors for vertex pulling:
+///
+/// ```glsl
+/// layout(set = N, binding = 0) buffer Meshes {
+///     Mesh meshes[];
+/// } meshi_bindless_geometry;
+/// ```
+///
+/// Vertices and indices are sub-allocated from the shared buffers bound as
+/// `meshi_bindless_vertices` (`V vertices[]`) and `meshi_bindless_indices`
+/// (`uint indices[]`). `V` is the vertex layout and must be `#[repr(C)]`.
+pub struct ReservedBindlessGeometry<V: Copy + Default + 'static = Vertex> {
+    capacity: GeometryCapacity,
+    vertex_buffer: Handle<Buffer>,
+    index_buffer: Handle<Buffer>,
+    mesh_buffer: Handle<Buffer>,
+    vertices: Vec<V>,
+    indices: Vec<u32>,
+    meshes: Vec<Mesh>,
+    live: Vec<bool>,
+    available: Vec<u32>,
+    dirty: bool,
+    vertex_layout: Option<StructLayout>,
+}
+
+/// A shared geometry buffer exposed under its own reserved name.
+pub struct ReservedGeometryBuffer {
+    name: &'static str,
+    buffer: Handle<Buffer>,
+    layout: Option<ReservedLayout>,
+}
+
+impl ReservedBindlessGeometry {
+    pub fn new(ctx: &mut Context) -> Self {
+        let mut geometry = Self::with_capacity(ctx, GeometryCapacity::default());
+        geometry.set_vertex_layout(Some(Vertex::shader_layout()));
+        geometry
+    }
+}
+
+impl<V: Copy + Default + 'static> ReservedBindlessGeometry<V> {
+    pub fn with_capacity(ctx: &mut Context, capacity: GeometryCapacity) -> Self {
+        let mut make = |debug_name: &str, byte_size: usize| {
+            ctx.make_buffer(&BufferInfo {
+                debug_name,
+                byte_size: byte_size as u32,
+                visibility: MemoryVisibility::CpuAndGpu,
+                usage: BufferUsage::STORAGE,
+                initial_data: None,
+            })
+            .expect("Failed making geometry buffer")
+        };
+
+        let vertex_buffer = make(
+            "[FURIKAKE] Bindless Vertices",
+            capacity.vertices as usize * std::mem::size_of::<V>(),
+        );
+        let index_buffer = make(
+            "[FURIKAKE] Bindless Indices",
+            capacity.indices as usize * std::mem::size_of::<u32>(),
+        );
+        let mesh_buffer = make(
+            "[FURIKAKE] Bindless Meshes",
+            capacity.meshes as usize * std::mem::size_of::<Mesh>(),
+        );
+
+        Self {
+            capacity,
+            vertex_buffer,
+            index_buffer,
+            mesh_buffer,
+            vertices: Vec::new(),
+            indices: Vec::new(),
+            meshes: Vec::new(),
+            live: Vec::new(),
+            available: Vec::new(),
+            dirty: false,
+            vertex_layout: None,
+        }
+    }
+
+    /// Layout of `V` for the resolver to check against the shader. Custom
+    /// vertex layouts start unchecked; pass `V::shader_layout()` to opt in
+    /// before taking [`Self::vertex_reservation`].
+    pub fn set_vertex_layout(&mut self, layout: Option<StructLayout>) {
+        self.vertex_layout = layout;
+    }
+
+    pub fn vertex_reservation(&self) -> ReservedGeometryBuffer {
+        ReservedGeometryBuffer {
+            name: "meshi_bindless_vertices",
+            buffer: self.vertex_buffer,
+            layout: self
+                .vertex_layout
+                .clone()
+                .map(|element| ReservedLayout::Elements { header: 0, element }),
+        }
+    }
+
+    pub fn index_reservation(&self) -> ReservedGeometryBuffer {
+        ReservedGeometryBuffer {
+            name: "meshi_bindless_indices",
+            buffer: self.index_buffer,
+            layout: Some(ReservedLayout::trailing_array(
+                StructLayout::default(),
+                "indices",
+                std::mem::size_of::<u32>() as u32,
+            )),
+        }
+    }
+
+    /// Copies a mesh into the shared buffers, compacting first if the tail of
+    /// either buffer is too small.
+    pub fn add_mesh(
+        &mut self,
+        vertices: &[V],
+        indices: &[u32],
+    ) -> Result<Handle<Mesh>, FurikakeError> {
+        if !self.fits(vertices.len(), indices.len()) {
+            self.compact();
+        }
+
+        let full = || FurikakeError::ReservationFull {
+            name: "meshi_bindless_geometry".to_string(),
+        };
+        let out_of_slots =
+            self.available.is_empty() && self.meshes.len() >= self.capacity.meshes as usize;
+        if out_of_slots || !self.fits(vertices.len(), indices.len()) {
+            return Err(full());
+        }
+
+        // Check the handle before touching the buffers so a full pool is left as it was.
+        let slot = match self.available.last() {
+            Some(slot) => *slot,
+            None => self.meshes.len() as u32,
+        };
+        let handle = super::slot_handle(slot).ok_or_else(full)?;
+
+        let mesh = Mesh {
+            vertex_offset: self.vertices.len() as u32,
+            vertex_count: vertices.len() as u32,
+            index_offset: self.indices.len() as u32,
+            index_count: indices.len() as u32,
+        };
+        self.vertices.extend_from_slice(vertices);
+        self.indices.extend_from_slice(indices);
+
+        if self.available.pop().is_none() {
+            self.meshes.push(Mesh::default());
+            self.live.push(false);
+        }
+        self.meshes[slot as usize] = mesh;
+        self.live[slot as usize] = true;
+        self.dirty = true;
+        Ok(handle)
+    }
+
+    /// Releases a mesh. Its storage is reclaimed by the next [`Self::compact`].
+    pub fn remove_mesh(&mut self, mesh: Handle<Mesh>) {
+        let slot = mesh.slot as usize;
+        if mesh.valid() && self.live.get(slot).copied().unwrap_or(false) {
+            self.live[slot] = false;
+            self.meshes[slot] = Mesh::default();
+            self.available.push(u32::from(mesh.slot));
+            self.dirty = true;
+        }
+    }
+
+    /// Mesh descriptors indexed by handle slot. Freed slots are zeroed.
+    pub fn meshes(&self) -> &[Mesh] {
+        &self.meshes
+    }
+
+    pub fn mesh(&self, handle: Handle<Mesh>) -> &Mesh {
+        &self.meshes[handle.slot as usize]
+    }
+
+    pub fn vertices(&self, handle: Handle<Mesh>) -> &[V] {
+        let mesh = self.mesh(handle);
+        &self.vertices
+            [mesh.vertex_offset as usize..(mesh.vertex_offset + mesh.vertex_count) as usize]
+    }
+
+    pub fn indices(&self, handle: Handle<Mesh>) -> &[u32] {
+        let mesh = self.mesh(handle);
+        &self.indices[mesh.index_offset as usize..(mesh.index_offset + mesh.index_count) as usize]
+    }
+
+    /// Vertices and indices currently allocated, including freed holes.
+    pub fn used(&self) -> (u32, u32) {
+        (self.vertices.len() as u32, self.indices.len() as u32)
+    }
+
+    /// Slides live meshes down over freed ranges and rewrites their offsets.
+    /// Mesh handles stay valid.
+    pub fn compact(&mut self) {
+        let mut order: Vec<usize> = (0..self.meshes.len())
+            .filter(|slot| self.live[*slot])
+            .collect();
+        order.sort_by_key(|slot| self.meshes[*slot].vertex_offset);
+
+        let mut vertices = Vec::with_capacity(self.vertices.len());
+        let mut indices = Vec::with_capacity(self.indices.len());
+        for slot in order {
+            let mesh = &mut self.meshes[slot];
+            let vertex_start = mesh.vertex_offset as usize;
+            let index_start = mesh.index_offset as usize;
+            mesh.vertex_offset = vertices.len() as u32;
+            mesh.index_offset = indices.len() as u32;
+            vertices.extend_from_slice(
+                &self.vertices[vertex_start..vertex_start + mesh.vertex_count as usize],
+            );
+            indices.extend_from_slice(
+                &self.indices[index_start..index_start + mesh.index_count as usize],
+            );
+        }
+
+        self.vertices = vertices;
+        self.indices = indices;
+        self.dirty = true;
+    }
+
+    pub fn vertex_buffer(&self) -> Handle<Buffer> {
+        self.vertex_buffer
+    }
+
+    pub fn index_buffer(&self) -> Handle<Buffer> {
+        self.index_buffer
+    }
+
+    pub fn mesh_buffer(&self) -> Handle<Buffer> {
+        self.mesh_buffer
+    }
+
+    fn fits(&self, vertex_count: usize, index_count: usize) -> bool {
+        self.vertices.len() + vertex_count <= self.capacity.vertices as usize
+            && self.indices.len() + index_count <= self.capacity.indices as usize
+    }
+}
+
+fn write_buffer<T: Copy>(
+    ctx: &mut Context,
+    buffer: Handle<Buffer>,
+    data: &[T],
+) -> Result<(), FurikakeError> {
+    let mapped = ctx
+        .map_buffer_mut::<T>(buffer)
+        .map_err(FurikakeError::buffer_map_failed)?;
+    mapped[..data.len()].copy_from_slice(data);
+    ctx.unmap_buffer(buffer)
+        .map_err(FurikakeError::buffer_unmap_failed)?;
+    Ok(())
+}
+
+impl<V: Copy + Default + 'static> ReservedItem for ReservedBindlessGeometry<V> {
+    fn name(&self) -> String {
+        "meshi_bindless_geometry".to_string()
+    }
+
+    fn update(&mut self, ctx: &mut Context) -> Result<(), FurikakeError> {
+        if !self.dirty {
+            return Ok(());
+        }
+
+        write_buffer(ctx, self.vertex_buffer, &self.vertices)?;
+        write_buffer(ctx, self.index_buffer, &self.indices)?;
+        write_buffer(ctx, self.mesh_buffer, &self.meshes)?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    fn binding(&self) -> ReservedBinding<'_> {
+        ReservedBinding::Binding(BindingInfo {
+            resource: ShaderResource::StorageBuffer(self.mesh_buffer),
+            binding: 0,
+        })
+    }
+
+    fn layout(&self) -> Option<ReservedLayout> {
+        Some(ReservedLayout::Elements {
+            header: 0,
+            element: Mesh::shader_layout(),
+        })
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+impl ReservedItem for ReservedGeometryBuffer {
+    fn name(&self) -> String {
+        self.name.to_string()
+    }
+
+    fn update(&mut self, _ctx: &mut Context) -> Result<(), FurikakeError> {
+        // Written by `ReservedBindlessGeometry::update`.
+        Ok(())
+    }
+
+    fn binding(&self) -> ReservedBinding<'_> {
+        ReservedBinding::Binding(BindingInfo {
+            resource: ShaderResource::StorageBuffer(self.buffer),
+            binding: 0,
+        })
+    }
+
+    fn layout(&self) -> Option<ReservedLayout> {
+        self.layout.clone()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reservations::MAX_SLOTS;
+    use dashi::ContextInfo;
+
+    fn triangle(x: f32) -> [Vertex; 3] {
+        [
+            Vertex {
+                u: x,
+                ..Default::default()
+            },
+            Vertex::default(),
+            Vertex::default(),
+        ]
+    }
+
+    #[test]
+    fn sub_allocates_meshes() {
+        let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
+        let mut geometry = ReservedBindlessGeometry::new(&mut ctx);
+
+        let first = geometry
+            .add_mesh(&triangle(1.0), &[0, 1, 2])
+            .expect("add first");
+        let second = geometry
+            .add_mesh(&triangle(2.0), &[2, 1, 0])
+            .expect("add second");
+
+        assert_eq!(geometry.mesh(first).vertex_offset, 0);
+        assert_eq!(geometry.mesh(second).vertex_offset, 3);
+        assert_eq!(geometry.mesh(second).index_offset, 3);
+        assert_eq!(geometry.indices(second), &[2, 1, 0]);
+
+        geometry.update(&mut ctx).expect("upload geometry");
+    }
+
+    #[test]
+    fn compacts_freed_ranges() {
+        let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
+        let mut geometry = ReservedBindlessGeometry::<Vertex>::with_capacity(
+            &mut ctx,
+            GeometryCapacity {
+                vertices: 6,
+                indices: 6,
+                meshes: 4,
+            },
+        );
+
+        let first = geometry
+            .add_mesh(&triangle(1.0), &[0, 1, 2])
+            .expect("add first");
+        let second = geometry
+            .add_mesh(&triangle(2.0), &[0, 1, 2])
+            .expect("add second");
+        assert!(matches!(
+            geometry.add_mesh(&triangle(3.0), &[0, 1, 2]),
+            Err(FurikakeError::ReservationFull { .. })
+        ));
+
+        geometry.remove_mesh(first);
+        let third = geometry
+            .add_mesh(&triangle(3.0), &[0, 1, 2])
+            .expect("compaction frees space");
+
+        assert_eq!(geometry.mesh(second).vertex_offset, 0);
+        assert_eq!(geometry.vertices(second)[0].u, 2.0);
+        assert_eq!(geometry.mesh(third).vertex_offset, 3);
+        assert_eq!(geometry.vertices(third)[0].u, 3.0);
+        assert_eq!(geometry.used(), (6, 6));
+    }
+
+    #[test]
+    fn leaves_the_pool_untouched_past_the_handle_range() {
+        let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
+        let mut geometry = ReservedBindlessGeometry::<Vertex>::with_capacity(
+            &mut ctx,
+            GeometryCapacity {
+                vertices: 3,
+                indices: 3,
+                meshes: MAX_SLOTS as u32 + 1,
+            },
+        );
+        for _ in 0..MAX_SLOTS {
+            geometry.add_mesh(&[], &[]).expect("slot in range");
+        }
+
+        assert!(matches!(
+            geometry.add_mesh(&triangle(1.0), &[0, 1, 2]),
+            Err(FurikakeError::ReservationFull { .. })
+        ));
+        assert_eq!(geometry.used(), (0, 0));
+        assert_eq!(geometry.meshes().len(), MAX_SLOTS);
+    }
+}
@@ -0,0 +1,396 @@
+#![allow(dead_code)]
+
+use dashi::{
+    BindingInfo, Buffer, BufferInfo, BufferUsage, Context, Handle, MemoryVisibility, ShaderResource,
+};
+
+use crate::{
+    error::FurikakeError,
+    resolver::{ReservedLayout, ShaderLayout},
+    types::{DrawIndexedIndirectCommand, INSTANCE_VISIBLE, Instance, Mesh},
+};
+
+use super::{ReservedBinding, ReservedItem};
+
+/// Per-instance records in draw order:
+///
+/// ```glsl
+/// layout(set = N, binding = 0) buffer Instances {
+///     Instance instances[];
+/// } meshi_bindless_instances;
+/// ```
+///
+/// [`ReservedBindlessInstances::build_draws`] sorts visible instances by mesh
+/// and emits one indexed indirect command per mesh into
+/// `meshi_indirect_draws`. Each command's `first_instance` points at its run
+/// of records, so `instances[gl_InstanceIndex]` is the instance being drawn.
+pub struct ReservedBindlessInstances {
+    capacity: usize,
+    instance_buffer: Handle<Buffer>,
+    command_buffer: Handle<Buffer>,
+    instances: Vec<Instance>,
+    // Dense index for each handle slot, `None` when the slot is free.
+    dense_index: Vec<Option<u32>>,
+    // Handle slot owning each dense index.
+    owners: Vec<u32>,
+    available: Vec<u32>,
+    draw_order: Vec<Instance>,
+    commands: Vec<DrawIndexedIndirectCommand>,
+    dirty: bool,
+}
+
+/// `meshi_indirect_draws`: the `DrawIndexedIndirectCommand` array filled by
+/// [`ReservedBindlessInstances`]. Bound as storage so culling shaders can
+/// rewrite instance counts.
+pub struct ReservedIndirectDraws {
+    buffer: Handle<Buffer>,
+}
+
+impl ReservedBindlessInstances {
+    const DEFAULT_CAPACITY: usize = 16384;
+
+    pub fn new(ctx: &mut Context) -> Self {
+        Self::with_capacity(ctx, Self::DEFAULT_CAPACITY)
+    }
+
+    pub fn with_capacity(ctx: &mut Context, capacity: usize) -> Self {
+        let instance_buffer = ctx
+            .make_buffer(&BufferInfo {
+                debug_name: "[FURIKAKE] Bindless Instances",
+                byte_size: (capacity * std::mem::size_of::<Instance>()) as u32,
+                visibility: MemoryVisibility::CpuAndGpu,
+                usage: BufferUsage::STORAGE,
+                initial_data: None,
+            })
+            .expect("Failed making instance buffer");
+
+        // Indirect buffers also need indirect usage, so take the default usage.
+        let command_buffer = ctx
+            .make_buffer(&BufferInfo {
+                debug_name: "[FURIKAKE] Indirect Draws",
+                byte_size: (capacity * std::mem::size_of::<DrawIndexedIndirectCommand>()) as u32,
+                visibility: MemoryVisibility::CpuAndGpu,
+                ..Default::default()
+            })
+            .expect("Failed making indirect draw buffer");
+
+        Self {
+            capacity,
+            instance_buffer,
+            command_buffer,
+            instances: Vec::new(),
+            dense_index: Vec::new(),
+            owners: Vec::new(),
+            available: Vec::new(),
+            draw_order: Vec::new(),
+            commands: Vec::new(),
+            dirty: false,
+        }
+    }
+
+    pub fn indirect_reservation(&self) -> ReservedIndirectDraws {
+        ReservedIndirectDraws {
+            buffer: self.command_buffer,
+        }
+    }
+
+    pub fn add_instance(&mut self, instance: Instance) -> Result<Handle<Instance>, FurikakeError> {
+        let full = || FurikakeError::ReservationFull {
+            name: "meshi_bindless_instances".to_string(),
+        };
+        if self.instances.len() >= self.capacity {
+            return Err(full());
+        }
+
+        let slot = match self.available.last() {
+            Some(slot) => *slot,
+            None => self.dense_index.len() as u32,
+        };
+        let handle = super::slot_handle(slot).ok_or_else(full)?;
+
+        if self.available.pop().is_none() {
+            self.dense_index.push(None);
+        }
+        self.dense_index[slot as usize] = Some(self.instances.len() as u32);
+        self.owners.push(slot);
+        self.instances.push(instance);
+        Ok(handle)
+    }
+
+    pub fn remove_instance(&mut self, instance: Handle<Instance>) {
+        let Some(index) = self.instance_index(instance) else {
+            return;
+        };
+
+        let index = index as usize;
+        self.instances.swap_remove(index);
+        self.owners.swap_remove(index);
+        if let Some(moved) = self.owners.get(index) {
+            self.dense_index[*moved as usize] = Some(index as u32);
+        }
+
+        self.dense_index[instance.slot as usize] = None;
+        self.available.push(u32::from(instance.slot));
+    }
+
+    pub fn instance(&self, handle: Handle<Instance>) -> &Instance {
+        let index = self
+            .instance_index(handle)
+            .expect("instance handle was removed");
+        &self.instances[index as usize]
+    }
+
+    pub fn instance_mut(&mut self, handle: Handle<Instance>) -> &mut Instance {
+        let index = self
+            .instance_index(handle)
+            .expect("instance handle was removed");
+        &mut self.instances[index as usize]
+    }
+
+    /// Rebuilds the draw-ordered instance list and indirect commands from the
+    /// current instances. `meshes` is indexed by `Instance::mesh_index`, as
+    /// returned by `ReservedBindlessGeometry::meshes`. Uploaded on `update()`.
+    pub fn build_draws(&mut self, meshes: &[Mesh]) {
+        self.draw_order.clear();
+        self.draw_order.extend(
+            self.instances
+                .iter()
+                .filter(|instance| instance.flags & INSTANCE_VISIBLE != 0)
+                .filter(|instance| (instance.mesh_index as usize) < meshes.len()),
+        );
+        self.draw_order.sort_by_key(|instance| instance.mesh_index);
+
+        self.commands.clear();
+        for (first, instance) in self.draw_order.iter().enumerate() {
+            match self.commands.last_mut() {
+                Some(command)
+                    if self.draw_order[command.first_instance as usize].mesh_index
+                        == instance.mesh_index =>
+                {
+                    command.instance_count += 1;
+                }
+                _ => {
+                    let mesh = &meshes[instance.mesh_index as usize];
+                    self.commands.push(DrawIndexedIndirectCommand {
+                        index_count: mesh.index_count,
+                        instance_count: 1,
+                        first_index: mesh.index_offset,
+                        vertex_offset: mesh.vertex_offset as i32,
+                        first_instance: first as u32,
+                    });
+                }
+            }
+        }
+
+        self.dirty = true;
+    }
+
+    pub fn draw_count(&self) -> u32 {
+        self.commands.len() as u32
+    }
+
+    pub fn commands(&self) -> &[DrawIndexedIndirectCommand] {
+        &self.commands
+    }
+
+    /// Instances in the order uploaded to the GPU.
+    pub fn draw_order(&self) -> &[Instance] {
+        &self.draw_order
+    }
+
+    pub fn instance_buffer(&self) -> Handle<Buffer> {
+        self.instance_buffer
+    }
+
+    pub fn command_buffer(&self) -> Handle<Buffer> {
+        self.command_buffer
+    }
+
+    fn instance_index(&self, handle: Handle<Instance>) -> Option<u32> {
+        self.dense_index
+            .get(handle.slot as usize)
+            .copied()
+            .flatten()
+    }
+}
+
+impl ReservedItem for ReservedBindlessInstances {
+    fn name(&self) -> String {
+        "meshi_bindless_instances".to_string()
+    }
+
+    fn update(&mut self, ctx: &mut Context) -> Result<(), FurikakeError> {
+        if !self.dirty {
+            return Ok(());
+        }
+
+        let instances = ctx
+            .map_buffer_mut::<Instance>(self.instance_buffer)
+            .map_err(FurikakeError::buffer_map_failed)?;
+        instances[..self.draw_order.len()].copy_from_slice(&self.draw_order);
+        ctx.unmap_buffer(self.instance_buffer)
+            .map_err(FurikakeError::buffer_unmap_failed)?;
+
+        let commands = ctx
+            .map_buffer_mut::<DrawIndexedIndirectCommand>(self.command_buffer)
+            .map_err(FurikakeError::buffer_map_failed)?;
+        commands[..self.commands.len()].copy_from_slice(&self.commands);
+        ctx.unmap_buffer(self.command_buffer)
+            .map_err(FurikakeError::buffer_unmap_failed)?;
+
+        self.dirty = false;
+        Ok(())
+    }
+
+    fn binding(&self) -> ReservedBinding<'_> {
+        ReservedBinding::Binding(BindingInfo {
+            resource: ShaderResource::StorageBuffer(self.instance_buffer),
+            binding: 0,
+        })
+    }
+
+    fn layout(&self) -> Option<ReservedLayout> {
+        Some(ReservedLayout::Elements {
+            header: 0,
+            element: Instance::shader_layout(),
+        })
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+impl ReservedItem for ReservedIndirectDraws {
+    fn name(&self) -> String {
+        "meshi_indirect_draws".to_string()
+    }
+
+    fn update(&mut self, _ctx: &mut Context) -> Result<(), FurikakeError> {
+        // Written by `ReservedBindlessInstances::update`.
+        Ok(())
+    }
+
+    fn binding(&self) -> ReservedBinding<'_> {
+        ReservedBinding::Binding(BindingInfo {
+            resource: ShaderResource::StorageBuffer(self.buffer),
+            binding: 0,
+        })
+    }
+
+    fn layout(&self) -> Option<ReservedLayout> {
+        Some(ReservedLayout::Elements {
+            header: 0,
+            element: DrawIndexedIndirectCommand::shader_layout(),
+        })
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reservations::MAX_SLOTS;
+    use dashi::ContextInfo;
+
+    fn instance(mesh_index: u32, transform_index: u32) -> Instance {
+        Instance {
+            transform_index,
+            material_index: 0,
+            mesh_index,
+            flags: INSTANCE_VISIBLE,
+        }
+    }
+
+    #[test]
+    fn groups_instances_by_mesh() {
+        let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
+        let mut instances = ReservedBindlessInstances::with_capacity(&mut ctx, 16);
+        let meshes = [
+            Mesh {
+                vertex_offset: 0,
+                vertex_count: 3,
+                index_offset: 0,
+                index_count: 3,
+            },
+            Mesh {
+                vertex_offset: 3,
+                vertex_count: 4,
+                index_offset: 3,
+                index_count: 6,
+            },
+        ];
+
+        instances.add_instance(instance(1, 0)).expect("add");
+        instances.add_instance(instance(0, 1)).expect("add");
+        let hidden = instances.add_instance(instance(1, 2)).expect("add");
+        instances.add_instance(instance(1, 3)).expect("add");
+        instances.instance_mut(hidden).flags = 0;
+
+        instances.build_draws(&meshes);
+        instances.update(&mut ctx).expect("upload draws");
+
+        assert_eq!(instances.draw_count(), 2);
+        assert_eq!(
+            instances.commands(),
+            &[
+                DrawIndexedIndirectCommand {
+                    index_count: 3,
+                    instance_count: 1,
+                    first_index: 0,
+                    vertex_offset: 0,
+                    first_instance: 0,
+                },
+                DrawIndexedIndirectCommand {
+                    index_count: 6,
+                    instance_count: 2,
+                    first_index: 3,
+                    vertex_offset: 3,
+                    first_instance: 1,
+                },
+            ]
+        );
+        assert_eq!(instances.draw_order()[0].transform_index, 1);
+    }
+
+    #[test]
+    fn reports_full_instance_buffer() {
+        let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
+        let mut instances = ReservedBindlessInstances::with_capacity(&mut ctx, 1);
+
+        let first = instances.add_instance(instance(0, 0)).expect("add");
+        assert!(instances.add_instance(instance(0, 1)).is_err());
+
+        instances.remove_instance(first);
+        assert!(instances.add_instance(instance(0, 1)).is_ok());
+    }
+
+    #[test]
+    fn leaves_instances_untouched_past_the_handle_range() {
+        let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
+        let mut instances = ReservedBindlessInstances::with_capacity(&mut ctx, MAX_SLOTS + 1);
+        for _ in 0..MAX_SLOTS {
+            instances
+                .add_instance(instance(0, 0))
+                .expect("slot in range");
+        }
+
+        assert!(matches!(
+            instances.add_instance(instance(0, 0)),
+            Err(FurikakeError::ReservationFull { .. })
+        ));
+        instances.build_draws(&[Mesh::default()]);
+        assert_eq!(instances.commands()[0].instance_count, MAX_SLOTS as u32);
+    }
+}
@@ -0,0 +1,297 @@
+#![allow(dead_code)]
+
+use dashi::{
+    BindingInfo, Buffer, BufferInfo, BufferUsage, Context, Handle, MemoryVisibility, ShaderResource,
+};
+
+use crate::{
+    error::FurikakeError,
+    resolver::{ReservedLayout, ShaderLayout},
+    types::Light,
+};
+
+use super::{ReservedBinding, ReservedItem};
+
+/// Header written in front of the light array. Padded to 16 bytes so the
+/// std430 `Light lights[]` member that follows starts on its alignment.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct LightHeader {
+    light_count: u32,
+    _padding: [u32; 3],
+}
+
+/// Lights packed densely into one storage buffer:
+///
+/// ```glsl
+/// layout(set = N, binding = 0) buffer Lights {
+///     uint light_count;
+///     Light lights[];
+/// } meshi_bindless_lights;
+/// ```
+///
+/// Handles stay stable while removals swap the last light into the freed
+/// index, so shaders can loop over `0..light_count`. Each slot carries a
+/// generation bumped on removal, so a handle kept past `remove_light` no
+/// longer resolves once its slot is reused.
+pub struct ReservedBindlessLights {
+    buffer: Handle<Buffer>,
+    // Bumped whenever `buffer` is reallocated.
+    buffer_generation: u32,
+    capacity: usize,
+    lights: Vec<Light>,
+    // Dense index for each handle slot, `None` when the slot is free.
+    dense_index: Vec<Option<u32>>,
+    // Handle slot owning each dense index.
+    owners: Vec<u32>,
+    // Generation of each handle slot, bumped on removal.
+    generations: Vec<u16>,
+    available: Vec<u32>,
+}
+
+impl ReservedBindlessLights {
+    const START_SIZE: usize = 1024;
+
+    pub fn new(ctx: &mut Context) -> Self {
+        Self {
+            buffer: Self::make_light_buffer(ctx, Self::START_SIZE),
+            buffer_generation: 0,
+            capacity: Self::START_SIZE,
+            lights: Vec::with_capacity(Self::START_SIZE),
+            dense_index: Vec::new(),
+            owners: Vec::with_capacity(Self::START_SIZE),
+            generations: Vec::new(),
+            available: Vec::new(),
+        }
+    }
+
+    pub fn buffer(&self) -> Handle<Buffer> {
+        self.buffer
+    }
+
+    /// Changes whenever `update()` reallocates the light buffer. Bind groups
+    /// cooked under an older generation still point at the destroyed buffer.
+    pub fn buffer_generation(&self) -> u32 {
+        self.buffer_generation
+    }
+
+    pub fn light_count(&self) -> u32 {
+        self.lights.len() as u32
+    }
+
+    /// Dense index of `handle` in the shader-visible `lights[]` array, or
+    /// `None` when the handle was removed.
+    pub fn light_index(&self, handle: Handle<Light>) -> Option<u32> {
+        let slot = handle.slot as usize;
+        if self.generations.get(slot) != Some(&handle.generation) {
+            return None;
+        }
+        self.dense_index.get(slot).copied().flatten()
+    }
+
+    pub fn add_light(&mut self, light: Light) -> Result<Handle<Light>, FurikakeError> {
+        let slot = match self.available.last() {
+            Some(slot) => *slot,
+            None => self.dense_index.len() as u32,
+        };
+        let handle = super::slot_handle::<Light>(slot)
+            .ok_or_else(|| FurikakeError::ReservationFull { name: self.name() })?;
+        if self.available.pop().is_none() {
+            self.dense_index.push(None);
+            self.generations.push(0);
+        }
+
+        self.dense_index[slot as usize] = Some(self.lights.len() as u32);
+        self.owners.push(slot);
+        self.lights.push(light);
+        Ok(Handle::new(handle.slot, self.generations[slot as usize]))
+    }
+
+    pub fn remove_light(&mut self, light: Handle<Light>) {
+        let Some(index) = self.light_index(light) else {
+            return;
+        };
+
+        let index = index as usize;
+        self.lights.swap_remove(index);
+        self.owners.swap_remove(index);
+        if let Some(moved) = self.owners.get(index) {
+            self.dense_index[*moved as usize] = Some(index as u32);
+        }
+
+        self.dense_index[light.slot as usize] = None;
+        let generation = &mut self.generations[light.slot as usize];
+        *generation = generation.wrapping_add(1);
+        self.available.push(u32::from(light.slot));
+    }
+
+    pub fn light(&self, handle: Handle<Light>) -> Option<&Light> {
+        let index = self.light_index(handle)?;
+        self.lights.get(index as usize)
+    }
+
+    pub fn light_mut(&mut self, handle: Handle<Light>) -> Option<&mut Light> {
+        let index = self.light_index(handle)?;
+        self.lights.get_mut(index as usize)
+    }
+
+    /// Lights in shader order.
+    pub fn lights(&self) -> &[Light] {
+        &self.lights
+    }
+
+    fn make_light_buffer(ctx: &mut Context, capacity: usize) -> Handle<Buffer> {
+        ctx.make_buffer(&BufferInfo {
+            debug_name: "[FURIKAKE] Bindless Lights",
+            byte_size: Self::byte_size(capacity) as u32,
+            visibility: MemoryVisibility::CpuAndGpu,
+            usage: BufferUsage::STORAGE,
+            initial_data: None,
+        })
+        .expect("Failed making light buffer")
+    }
+
+    fn byte_size(capacity: usize) -> usize {
+        std::mem::size_of::<LightHeader>() + capacity * std::mem::size_of::<Light>()
+    }
+}
+
+impl ReservedItem for ReservedBindlessLights {
+    fn name(&self) -> String {
+        "meshi_bindless_lights".to_string()
+    }
+
+    /// Writes the light count and the packed lights. Growing past the current
+    /// capacity reallocates the buffer and bumps `buffer_generation()`, so
+    /// bind groups must be cooked again.
+    fn update(&mut self, ctx: &mut Context) -> Result<(), FurikakeError> {
+        if self.lights.len() > self.capacity {
+            self.capacity = self.lights.len().next_power_of_two();
+            ctx.destroy_buffer(self.buffer);
+            self.buffer = Self::make_light_buffer(ctx, self.capacity);
+            self.buffer_generation += 1;
+        }
+
+        let header = [LightHeader {
+            light_count: self.light_count(),
+            ..Default::default()
+        }];
+        let header_bytes = unsafe { header.align_to::<u8>().1 };
+        let light_bytes = unsafe { self.lights.align_to::<u8>().1 };
+
+        let mapped = ctx
+            .map_buffer_mut::<u8>(self.buffer)
+            .map_err(FurikakeError::buffer_map_failed)?;
+        mapped[..header_bytes.len()].copy_from_slice(header_bytes);
+        mapped[header_bytes.len()..header_bytes.len() + light_bytes.len()]
+            .copy_from_slice(light_bytes);
+        ctx.unmap_buffer(self.buffer)
+            .map_err(FurikakeError::buffer_unmap_failed)?;
+
+        Ok(())
+    }
+
+    fn binding(&self) -> ReservedBinding<'_> {
+        ReservedBinding::Binding(BindingInfo {
+            resource: ShaderResource::StorageBuffer(self.buffer),
+            binding: 0,
+        })
+    }
+
+    fn layout(&self) -> Option<ReservedLayout> {
+        Some(ReservedLayout::Elements {
+            header: std::mem::size_of::<LightHeader>() as u32,
+            element: Light::shader_layout(),
+        })
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::LightKind;
+    use dashi::ContextInfo;
+    use glam::Vec3;
+
+    #[test]
+    fn keeps_lights_packed_across_removal() {
+        let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
+        let mut lights = ReservedBindlessLights::new(&mut ctx);
+
+        let sun = lights
+            .add_light(Light::directional(Vec3::NEG_Y, Vec3::ONE, 3.0))
+            .expect("add sun");
+        let lamp = lights
+            .add_light(Light::point(Vec3::ZERO, Vec3::X, 10.0, 5.0))
+            .expect("add lamp");
+        let torch = lights
+            .add_light(Light::spot(
+                Vec3::Y,
+                Vec3::NEG_Y,
+                Vec3::ONE,
+                4.0,
+                8.0,
+                0.2,
+                0.4,
+            ))
+            .expect("add torch");
+        assert_eq!(lights.light_count(), 3);
+
+        lights.remove_light(sun);
+        assert_eq!(lights.light_count(), 2);
+        assert_eq!(lights.light_index(sun), None);
+        assert_eq!(lights.light_index(torch), Some(0));
+        assert!(lights.light(sun).is_none());
+        assert_eq!(
+            lights.light(torch).map(Light::light_kind),
+            Some(LightKind::Spot)
+        );
+        assert_eq!(lights.light(lamp).map(|light| light.range), Some(5.0));
+
+        lights.light_mut(lamp).expect("live lamp").intensity = 20.0;
+        lights.update(&mut ctx).expect("update lights");
+
+        let mapped = ctx
+            .map_buffer::<u32>(lights.buffer())
+            .expect("map light buffer");
+        assert_eq!(mapped[0], 2);
+        ctx.unmap_buffer(lights.buffer())
+            .expect("unmap light buffer");
+
+        let reused = lights.add_light(Light::default()).expect("reuse slot");
+        assert_eq!(reused.slot, sun.slot);
+        assert_ne!(reused.generation, sun.generation);
+        assert!(lights.light(sun).is_none());
+        assert_eq!(lights.light_index(reused), Some(2));
+    }
+
+    #[test]
+    fn regrowing_replaces_the_buffer() {
+        let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
+        let mut lights = ReservedBindlessLights::new(&mut ctx);
+
+        lights.update(&mut ctx).expect("update lights");
+        assert_eq!(lights.buffer_generation(), 0);
+
+        for _ in 0..=ReservedBindlessLights::START_SIZE {
+            lights.add_light(Light::default()).expect("add light");
+        }
+        lights.update(&mut ctx).expect("regrow lights");
+
+        assert_eq!(lights.buffer_generation(), 1);
+        let mapped = ctx
+            .map_buffer::<u32>(lights.buffer())
+            .expect("map light buffer");
+        assert_eq!(mapped[0], ReservedBindlessLights::START_SIZE as u32 + 1);
+        ctx.unmap_buffer(lights.buffer())
+            .expect("unmap light buffer");
+    }
+}
@@ -1,131 +1,104 @@
 #![allow(dead_code)]
 
-use std::ptr::NonNull;
-
-use dashi::{BufferInfo, Context, Handle, IndexedBindingInfo, IndexedResource, ShaderResource};
-
-use crate::types::Material;
-
-use super::{ReservedBinding, ReservedItem};
-
-pub struct ReservedBindlessMaterials {
-    ctx: NonNull<Context>,
-    device_material_data: Vec<IndexedResource>,
-    host_material_data: Vec<NonNull<Material>>,
-    available: Vec<u16>,
+use dashi::{Context, Handle};
+
+use crate::{
+    error::FurikakeError,
+    resolver::{ReservedLayout, ShaderLayout, StructLayout},
+    types::Material,
+};
+
+use super::{
+    ReservedBinding, ReservedItem,
+    bindless_pool::{BindlessMemory, BindlessPool, UploadStats},
+};
+
+/// Bindless pool of material records.
+///
+/// `M` is the material layout uploaded to the GPU and defaults to
+/// [`Material`]. Custom layouts must be `#[repr(C)]` and match the shader-side
+/// struct declared under the reservation's name.
+pub struct ReservedBindlessMaterials<M: Copy + Default + 'static = Material> {
+    name: String,
+    pool: BindlessPool<M>,
+    layout: Option<StructLayout>,
 }
 
 impl ReservedBindlessMaterials {
     pub fn new(ctx: &mut Context) -> Self {
-        const START_SIZE: usize = 512;
-
-        let mut d_data = Vec::with_capacity(START_SIZE);
-        let mut h_data = Vec::with_capacity(START_SIZE);
-        let available: Vec<u16> = (0..START_SIZE as u16).collect();
-
-        for i in 0..START_SIZE {
-            let default = [Material::default()];
-            let buf = ctx
-                .make_buffer(&BufferInfo {
-                    debug_name: &format!("[FURIKAKE] Bindless Material {}", i),
-                    byte_size: std::mem::size_of::<Material>() as u32,
-                    visibility: dashi::MemoryVisibility::CpuAndGpu,
-                    usage: dashi::BufferUsage::STORAGE,
-                    initial_data: Some(unsafe { default.align_to::<u8>().1 }),
-                })
-                .expect("Failed making material buffer");
-
-            let h = ctx
-                .map_buffer_mut::<Material>(buf)
-                .expect("Failed to map buffer");
-            let nnm = NonNull::new(h.as_mut_ptr()).expect("NonNull failed check for material map!");
-
-            h_data.push(nnm);
-            d_data.push(IndexedResource {
-                resource: ShaderResource::StorageBuffer(buf),
-                slot: i as u32,
-            });
-        }
+        let mut materials = Self::with_name(ctx, "meshi_bindless_materials");
+        materials.set_layout(Some(Material::shader_layout()));
+        materials
+    }
+}
 
+impl<M: Copy + Default + 'static> ReservedBindlessMaterials<M> {
+    /// Creates a host-mapped materials pool exposed to shaders as `name`.
+    pub fn with_name(ctx: &mut Context, name: &str) -> Self {
+        Self::with_memory(ctx, name, BindlessMemory::HostMapped)
+    }
+
+    pub fn with_memory(ctx: &mut Context, name: &str, memory: BindlessMemory) -> Self {
         Self {
-            ctx: NonNull::new(ctx).expect("NonNull failed check"),
-            device_material_data: d_data,
-            host_material_data: h_data,
-            available,
+            name: name.to_string(),
+            pool: BindlessPool::new(ctx, "Material", memory),
+            layout: None,
         }
     }
 
+    /// Layout of `M` for the resolver to check against the shader. Custom
+    /// layouts start unchecked; pass `M::shader_layout()` to opt in.
+    pub fn set_layout(&mut self, layout: Option<StructLayout>) {
+        self.layout = layout;
+    }
+
     pub fn extend(&mut self) {
-        let ctx: &mut Context = unsafe { self.ctx.as_mut() };
-        if self.available.is_empty() {
-            const EXTENSION_SIZE: usize = 128;
-            let start = self.host_material_data.len();
-            let end = start + EXTENSION_SIZE;
-            for i in start..end {
-                let default = [Material::default()];
-                let buf = ctx
-                    .make_buffer(&BufferInfo {
-                        debug_name: &format!("[FURIKAKE] Bindless Material {}", i),
-                        byte_size: std::mem::size_of::<Material>() as u32,
-                        visibility: dashi::MemoryVisibility::CpuAndGpu,
-                        usage: dashi::BufferUsage::STORAGE,
-                        initial_data: Some(unsafe { default.align_to::<u8>().1 }),
-                    })
-                    .expect("Failed making material buffer");
-
-                let h = ctx
-                    .map_buffer_mut::<Material>(buf)
-                    .expect("Failed to map buffer");
-                let nnm =
-                    NonNull::new(h.as_mut_ptr()).expect("NonNull failed check for material map!");
-
-                self.host_material_data.push(nnm);
-                self.device_material_data.push(IndexedResource {
-                    resource: ShaderResource::StorageBuffer(buf),
-                    slot: i as u32,
-                });
-            }
-        }
+        self.pool.extend();
     }
 
-    pub fn remove_material(&mut self, material: Handle<Material>) {
-        if material.valid() && (material.slot as usize) < self.device_material_data.len() {
-            self.available.push(material.slot);
-        }
+    pub fn remove_material(&mut self, material: Handle<M>) {
+        self.pool.remove(material);
     }
 
-    pub fn add_material(&mut self) -> Handle<Material> {
-        if let Some(id) = self.available.pop() {
-            Handle::new(id, 0)
-        } else {
-            self.extend();
-            self.add_material()
-        }
+    pub fn add_material(&mut self) -> Result<Handle<M>, FurikakeError> {
+        self.pool
+            .add()
+            .ok_or_else(|| FurikakeError::ReservationFull {
+                name: self.name.clone(),
+            })
     }
 
-    pub fn material(&self, handle: Handle<Material>) -> &Material {
-        unsafe { self.host_material_data[handle.slot as usize].as_ref() }
+    pub fn material(&self, handle: Handle<M>) -> &M {
+        self.pool.get(handle.slot as usize)
     }
 
-    pub fn material_mut(&mut self, handle: Handle<Material>) -> &mut Material {
-        unsafe { self.host_material_data[handle.slot as usize].as_mut() }
+    pub fn material_mut(&mut self, handle: Handle<M>) -> &mut M {
+        self.pool.get_mut(handle.slot as usize)
+    }
+
+    /// Bytes and slots uploaded by the last `update()` in device-local mode.
+    pub fn upload_stats(&self) -> UploadStats {
+        self.pool.upload_stats()
     }
 }
 
-impl ReservedItem for ReservedBindlessMaterials {
+impl<M: Copy + Default + 'static> ReservedItem for ReservedBindlessMaterials<M> {
     fn name(&self) -> String {
-        "meshi_bindless_materials".to_string()
+        self.name.clone()
     }
 
-    fn update(&mut self, _ctx: &mut Context) -> Result<(), crate::error::FurikakeError> {
-        Ok(())
+    fn update(&mut self, ctx: &mut Context) -> Result<(), crate::error::FurikakeError> {
+        self.pool.update(ctx)
     }
 
     fn binding(&self) -> ReservedBinding<'_> {
-        ReservedBinding::BindlessBinding(IndexedBindingInfo {
-            resources: &self.device_material_data,
-            binding: 0,
-        })
+        self.pool.binding()
+    }
+
+    fn layout(&self) -> Option<ReservedLayout> {
+        self.layout
+            .clone()
+            .map(|element| ReservedLayout::Elements { header: 0, element })
     }
 
     fn as_any(&self) -> &dyn std::any::Any {
@@ -140,6 +113,7 @@ impl ReservedItem for ReservedBindlessMaterials {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::resolver::{compare_layouts, spirv::SpirvModule};
     use dashi::{Context, ContextInfo};
 
     #[test]
@@ -147,22 +121,54 @@ mod tests {
         let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
         let mut materials = ReservedBindlessMaterials::new(&mut ctx);
 
-        let first = materials.add_material();
-        let second = materials.add_material();
+        let first = materials.add_material().expect("free slot");
+        let second = materials.add_material().expect("free slot");
         assert_ne!(first.slot, second.slot);
 
         materials.remove_material(first);
-        let reused = materials.add_material();
+        let reused = materials.add_material().expect("free slot");
 
         assert_eq!(first.slot, reused.slot);
     }
 
+    #[repr(C)]
+    #[derive(Clone, Copy, Default)]
+    struct ToonMaterial {
+        ramp_texture_id: u32,
+        outline_width: f32,
+        bands: u32,
+        _padding: u32,
+    }
+
+    #[test]
+    fn supports_custom_material_layouts() {
+        let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
+        let mut materials =
+            ReservedBindlessMaterials::<ToonMaterial>::with_name(&mut ctx, "meshi_toon_materials");
+        assert_eq!(materials.name(), "meshi_toon_materials");
+
+        let handle = materials.add_material().expect("free slot");
+        {
+            let material = materials.material_mut(handle);
+            material.ramp_texture_id = 9;
+            material.outline_width = 0.5;
+            material.bands = 3;
+        }
+
+        materials.update(&mut ctx).expect("update materials");
+
+        let material = materials.material(handle);
+        assert_eq!(material.ramp_texture_id, 9);
+        assert_eq!(material.outline_width, 0.5);
+        assert_eq!(material.bands, 3);
+    }
+
     #[test]
     fn stores_texture_ids_only() {
         let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
         let mut materials = ReservedBindlessMaterials::new(&mut ctx);
 
-        let handle = materials.add_material();
+        let handle = materials.add_material().expect("free slot");
         {
             let material = materials.material_mut(handle);
             material.base_color_texture_id = 1;
@@ -181,4 +187,50 @@ mod tests {
         assert_eq!(material.occlusion_texture_id, 4);
         assert_eq!(material.emissive_texture_id, 5);
     }
+
+    #[test]
+    fn material_layout_matches_shader() {
+        let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
+        let spirv: &[u32] = inline_spirv::inline_spirv!(
+            r#"
+            #version 450 core
+            layout(local_size_x = 1) in;
+
+            struct Material {
+                uint base_color_texture_id;
+                uint normal_texture_id;
+                uint metallic_roughness_texture_id;
+                uint occlusion_texture_id;
+                uint emissive_texture_id;
+                uint _padding;
+            };
+            layout(set = 0, binding = 0) buffer Materials {
+                Material materials[];
+            } meshi_bindless_materials;
+
+            void main() {
+                meshi_bindless_materials.materials[0]._padding =
+                    meshi_bindless_materials.materials[0].base_color_texture_id;
+            }
+            "#,
+            comp
+        );
+
+        let module = SpirvModule::parse(spirv).expect("parse spirv");
+        let block = module
+            .variable_block("meshi_bindless_materials")
+            .expect("materials block");
+        let (element, stride) = block.elements.expect("material array");
+
+        let materials = ReservedBindlessMaterials::new(&mut ctx);
+        let Some(ReservedLayout::Elements { element: rust, .. }) = materials.layout() else {
+            panic!("materials describe their element layout");
+        };
+        assert_eq!(element.name, "Material");
+        assert_eq!(
+            compare_layouts("meshi_bindless_materials", &rust, &element),
+            Vec::new()
+        );
+        assert_eq!(stride, Some(rust.size));
+    }
 }
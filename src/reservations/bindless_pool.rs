@@ -0,0 +1,381 @@
+use std::ptr::NonNull;
+
+use dashi::driver::command::CopyBuffer;
+use dashi::{
+    Buffer, BufferInfo, BufferUsage, CommandQueueInfo2, CommandRing, CommandStream, Context,
+    Handle, IndexedBindingInfo, IndexedResource, MemoryVisibility, ShaderResource, SubmitInfo,
+};
+
+use crate::error::FurikakeError;
+
+use super::ReservedBinding;
+
+/// How a bindless pool backs its slots.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BindlessMemory {
+    /// Slots are persistently mapped `CpuAndGpu` buffers written in place.
+    #[default]
+    HostMapped,
+    /// Slots are device-local buffers. Writes land in a host shadow copy and
+    /// `update()` copies each dirty slot up from a persistent staging buffer.
+    DeviceLocal,
+}
+
+/// Upload counters for the last `update()` of a bindless pool. Every slot is
+/// its own buffer, so each dirty slot is one copy.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct UploadStats {
+    /// Bytes copied out of the staging buffer.
+    pub bytes: u64,
+    /// Slots written.
+    pub slots: u32,
+}
+
+enum HostData<T> {
+    Mapped(Vec<NonNull<T>>),
+    Shadow { data: Vec<T>, dirty: Vec<bool> },
+}
+
+/// Staging buffer and command ring a device-local pool keeps across updates.
+/// Slot `i` stages at byte `i * size_of::<T>()`.
+struct Upload {
+    ring: CommandRing,
+    staging: Handle<Buffer>,
+    byte_size: usize,
+    in_flight: bool,
+}
+
+/// Slot allocator and storage shared by the bindless reservations. Every slot
+/// is its own storage buffer so shaders index them as a descriptor array.
+pub(crate) struct BindlessPool<T: Copy + Default + 'static> {
+    ctx: NonNull<Context>,
+    label: &'static str,
+    memory: BindlessMemory,
+    device_data: Vec<IndexedResource>,
+    buffers: Vec<Handle<Buffer>>,
+    host_data: HostData<T>,
+    available: Vec<u32>,
+    upload: Option<Upload>,
+    stats: UploadStats,
+}
+
+impl<T: Copy + Default + 'static> BindlessPool<T> {
+    const START_SIZE: usize = 512;
+    const EXTENSION_SIZE: usize = 128;
+
+    pub fn new(ctx: &mut Context, label: &'static str, memory: BindlessMemory) -> Self {
+        let host_data = match memory {
+            BindlessMemory::HostMapped => HostData::Mapped(Vec::with_capacity(Self::START_SIZE)),
+            BindlessMemory::DeviceLocal => HostData::Shadow {
+                data: Vec::with_capacity(Self::START_SIZE),
+                dirty: Vec::with_capacity(Self::START_SIZE),
+            },
+        };
+
+        let mut pool = Self {
+            ctx: NonNull::new(ctx).expect("NonNull failed check"),
+            label,
+            memory,
+            device_data: Vec::with_capacity(Self::START_SIZE),
+            buffers: Vec::with_capacity(Self::START_SIZE),
+            host_data,
+            available: Vec::with_capacity(Self::START_SIZE),
+            upload: None,
+            stats: UploadStats::default(),
+        };
+        pool.grow(Self::START_SIZE);
+        pool
+    }
+
+    pub fn memory(&self) -> BindlessMemory {
+        self.memory
+    }
+
+    pub fn len(&self) -> usize {
+        self.device_data.len()
+    }
+
+    pub fn upload_stats(&self) -> UploadStats {
+        self.stats
+    }
+
+    pub fn extend(&mut self) {
+        if self.available.is_empty() {
+            self.grow(Self::EXTENSION_SIZE);
+        }
+    }
+
+    /// Grows the pool until `slot` is addressable, for pools whose slots
+    /// mirror another pool's handles rather than being allocated by `add`.
+    pub fn ensure_slot(&mut self, slot: usize) {
+        while self.len() <= slot && self.len() < super::MAX_SLOTS {
+            self.grow(Self::EXTENSION_SIZE);
+        }
+    }
+
+    /// Allocates a slot, growing the pool if needed. `None` once every
+    /// addressable slot is in use.
+    pub fn add(&mut self) -> Option<Handle<T>> {
+        self.extend();
+        super::slot_handle(self.available.pop()?)
+    }
+
+    pub fn remove(&mut self, handle: Handle<T>) {
+        if handle.valid() && (handle.slot as usize) < self.device_data.len() {
+            self.available.push(u32::from(handle.slot));
+        }
+    }
+
+    pub fn get(&self, slot: usize) -> &T {
+        match &self.host_data {
+            HostData::Mapped(data) => unsafe { data[slot].as_ref() },
+            HostData::Shadow { data, .. } => &data[slot],
+        }
+    }
+
+    /// Mutable access to a slot. In device-local mode this marks the slot dirty.
+    pub fn get_mut(&mut self, slot: usize) -> &mut T {
+        match &mut self.host_data {
+            HostData::Mapped(data) => unsafe { data[slot].as_mut() },
+            HostData::Shadow { data, dirty } => {
+                dirty[slot] = true;
+                &mut data[slot]
+            }
+        }
+    }
+
+    pub fn binding(&self) -> ReservedBinding<'_> {
+        ReservedBinding::BindlessBinding(IndexedBindingInfo {
+            resources: &self.device_data,
+            binding: 0,
+        })
+    }
+
+    /// Uploads dirty slots in device-local mode. Host-mapped pools are written
+    /// in place and have nothing to do.
+    ///
+    /// Dirty slots are not coalesced: every slot is its own buffer, so each
+    /// one is a separate copy. The copies are submitted without waiting;
+    /// [`Self::prepare_upload`] waits for the previous submission before the
+    /// staging buffer is rewritten, and that submission has normally finished
+    /// a frame later.
+    pub fn update(&mut self, ctx: &mut Context) -> Result<(), FurikakeError> {
+        self.stats = UploadStats::default();
+
+        let HostData::Shadow { data, dirty } = &mut self.host_data else {
+            return Ok(());
+        };
+
+        let pending: Vec<usize> = dirty
+            .iter()
+            .enumerate()
+            .filter_map(|(slot, flag)| flag.then_some(slot))
+            .collect();
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        let stride = std::mem::size_of::<T>();
+        let upload = Self::prepare_upload(&mut self.upload, ctx, self.label, data.len() * stride)?;
+        let staged = ctx
+            .map_buffer_mut::<u8>(upload.staging)
+            .map_err(FurikakeError::buffer_map_failed)?;
+        for slot in &pending {
+            let bytes = unsafe { std::slice::from_ref(&data[*slot]).align_to::<u8>().1 };
+            staged[slot * stride..(slot + 1) * stride].copy_from_slice(bytes);
+            dirty[*slot] = false;
+        }
+        ctx.unmap_buffer(upload.staging)
+            .map_err(FurikakeError::buffer_unmap_failed)?;
+
+        let staging = upload.staging;
+        let buffers = &self.buffers;
+        upload
+            .ring
+            .record(|list| {
+                let mut stream = CommandStream::new().begin();
+                for slot in &pending {
+                    stream.copy_buffers(&CopyBuffer {
+                        src: staging,
+                        dst: buffers[*slot],
+                        src_offset: (slot * stride) as u32,
+                        dst_offset: 0,
+                        amount: stride as u32,
+                    });
+                }
+                stream.end().append(list);
+            })
+            .map_err(FurikakeError::upload_failed)?;
+        upload
+            .ring
+            .submit(&SubmitInfo::default())
+            .map_err(FurikakeError::upload_failed)?;
+        upload.in_flight = true;
+
+        self.stats.bytes = (pending.len() * stride) as u64;
+        self.stats.slots = pending.len() as u32;
+        Ok(())
+    }
+
+    /// Returns the pool's upload resources once the previous upload has
+    /// finished, creating them on first use and regrowing the staging buffer
+    /// to `byte_size` when the pool has grown since.
+    fn prepare_upload<'a>(
+        upload: &'a mut Option<Upload>,
+        ctx: &mut Context,
+        label: &str,
+        byte_size: usize,
+    ) -> Result<&'a mut Upload, FurikakeError> {
+        if let Some(existing) = upload.as_mut().filter(|upload| upload.in_flight) {
+            existing
+                .ring
+                .wait_all()
+                .map_err(FurikakeError::upload_failed)?;
+            existing.in_flight = false;
+        }
+
+        if let Some(existing) = upload.take_if(|upload| upload.byte_size < byte_size) {
+            ctx.destroy_buffer(existing.staging);
+            *upload = Some(Upload {
+                byte_size,
+                staging: Self::make_staging(ctx, label, byte_size)?,
+                ..existing
+            });
+        }
+
+        match upload {
+            Some(upload) => Ok(upload),
+            None => {
+                let ring = ctx
+                    .make_command_ring(&CommandQueueInfo2 {
+                        debug_name: "[FURIKAKE] Bindless Upload",
+                        ..Default::default()
+                    })
+                    .map_err(FurikakeError::upload_failed)?;
+                Ok(upload.insert(Upload {
+                    ring,
+                    staging: Self::make_staging(ctx, label, byte_size)?,
+                    byte_size,
+                    in_flight: false,
+                }))
+            }
+        }
+    }
+
+    fn make_staging(
+        ctx: &mut Context,
+        label: &str,
+        byte_size: usize,
+    ) -> Result<Handle<Buffer>, FurikakeError> {
+        ctx.make_buffer(&BufferInfo {
+            debug_name: &format!("[FURIKAKE] Bindless {} Staging", label),
+            byte_size: byte_size as u32,
+            visibility: MemoryVisibility::CpuAndGpu,
+            ..Default::default()
+        })
+        .map_err(FurikakeError::upload_failed)
+    }
+
+    fn grow(&mut self, count: usize) {
+        let ctx: &mut Context = unsafe { self.ctx.as_mut() };
+        let visibility = match self.memory {
+            BindlessMemory::HostMapped => MemoryVisibility::CpuAndGpu,
+            BindlessMemory::DeviceLocal => MemoryVisibility::Gpu,
+        };
+
+        let start = self.device_data.len();
+        let count = count.min(super::MAX_SLOTS - start);
+        for i in start..start + count {
+            let default = [T::default()];
+            let buf = ctx
+                .make_buffer(&BufferInfo {
+                    debug_name: &format!("[FURIKAKE] Bindless {} {}", self.label, i),
+                    byte_size: std::mem::size_of::<T>() as u32,
+                    visibility,
+                    usage: BufferUsage::STORAGE,
+                    initial_data: Some(unsafe { default.align_to::<u8>().1 }),
+                })
+                .expect("Failed making bindless buffer");
+
+            match &mut self.host_data {
+                HostData::Mapped(data) => {
+                    let h = ctx.map_buffer_mut::<T>(buf).expect("Failed to map buffer");
+                    data.push(
+                        NonNull::new(h.as_mut_ptr())
+                            .expect("NonNull failed check for bindless map!"),
+                    );
+                }
+                HostData::Shadow { data, dirty } => {
+                    data.push(T::default());
+                    dirty.push(false);
+                }
+            }
+
+            self.buffers.push(buf);
+            self.device_data.push(IndexedResource {
+                resource: ShaderResource::StorageBuffer(buf),
+                slot: i as u32,
+            });
+            self.available.push(i as u32);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reservations::{MAX_SLOTS, slot_handle};
+    use dashi::ContextInfo;
+
+    #[test]
+    fn host_mapped_pools_skip_uploads() {
+        let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
+        let mut pool = BindlessPool::<[f32; 4]>::new(&mut ctx, "Test", BindlessMemory::HostMapped);
+
+        let handle = pool.add().expect("free slot");
+        pool.get_mut(handle.slot as usize)[0] = 1.0;
+        pool.update(&mut ctx).expect("update pool");
+
+        assert_eq!(pool.get(handle.slot as usize)[0], 1.0);
+        assert_eq!(pool.upload_stats(), UploadStats::default());
+    }
+
+    #[test]
+    fn uploads_dirty_device_local_slots() {
+        let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
+        let mut pool = BindlessPool::<[f32; 4]>::new(&mut ctx, "Test", BindlessMemory::DeviceLocal);
+
+        let a = pool.add().expect("free slot");
+        let b = pool.add().expect("free slot");
+        let c = pool.add().expect("free slot");
+        pool.get_mut(a.slot as usize)[0] = 1.0;
+        pool.get_mut(b.slot as usize)[0] = 2.0;
+        pool.get_mut(c.slot as usize)[0] = 3.0;
+        pool.get_mut(0)[0] = 4.0;
+
+        pool.update(&mut ctx).expect("upload dirty slots");
+        let stats = pool.upload_stats();
+        assert_eq!(stats.slots, 4);
+        assert_eq!(stats.bytes, 4 * std::mem::size_of::<[f32; 4]>() as u64);
+
+        pool.update(&mut ctx).expect("nothing left to upload");
+        assert_eq!(pool.upload_stats(), UploadStats::default());
+        assert_eq!(pool.get(b.slot as usize)[0], 2.0);
+
+        // Growing the pool regrows the staging buffer on the next upload.
+        let grown: Vec<_> = (0..pool.len())
+            .map(|_| pool.add().expect("free slot"))
+            .collect();
+        let last = grown.iter().map(|h| h.slot).max().expect("grown slots");
+        pool.get_mut(last as usize)[0] = 5.0;
+        pool.update(&mut ctx).expect("upload into grown pool");
+        assert_eq!(pool.upload_stats().slots, 1);
+    }
+
+    #[test]
+    fn slots_past_the_handle_range_have_no_handle() {
+        let last = MAX_SLOTS as u32 - 1;
+        assert_eq!(slot_handle::<u32>(last).map(|h| h.slot), Some(u16::MAX));
+        assert!(slot_handle::<u32>(last + 1).is_none());
+    }
+}
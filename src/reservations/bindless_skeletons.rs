@@ -0,0 +1,412 @@
+#![allow(dead_code)]
+
+use dashi::{
+    BindingInfo, Buffer, BufferInfo, BufferUsage, Context, Handle, MemoryVisibility, ShaderResource,
+};
+use glam::Mat4;
+
+use crate::{
+    error::FurikakeError,
+    resolver::{ReservedLayout, StructLayout},
+    scene::{SceneGraph, SceneNode},
+    types::Skeleton,
+};
+
+use super::{ReservedBinding, ReservedItem};
+
+/// Sizes of the skeleton buffers, fixed at creation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SkeletonCapacity {
+    pub joints: u32,
+    pub skeletons: u32,
+}
+
+impl Default for SkeletonCapacity {
+    fn default() -> Self {
+        Self {
+            joints: 16384,
+            skeletons: 1024,
+        }
+    }
+}
+
+struct SkeletonData {
+    inverse_bind: Vec<Mat4>,
+    joint_world: Vec<Mat4>,
+    skin: Vec<Mat4>,
+    dirty: bool,
+}
+
+/// Skeleton descriptors and their joint palettes:
+///
+/// ```glsl
+/// layout(set = N, binding = 0) buffer Skeletons {
+///     uvec2 skeletons[]; // joint offset, joint count
+/// } meshi_bindless_skeletons;
+///
+/// layout(set = N, binding = 1) buffer Joints {
+///     mat4 joints[];
+/// } meshi_bindless_joints;
+/// ```
+///
+/// Each skeleton owns a contiguous range of `joints`. `update()` computes
+/// `joint_world * inverse_bind` for every skeleton whose joints changed.
+pub struct ReservedBindlessSkeletons {
+    capacity: SkeletonCapacity,
+    skeleton_buffer: Handle<Buffer>,
+    joint_buffer: Handle<Buffer>,
+    descriptors: Vec<Skeleton>,
+    skeletons: Vec<Option<SkeletonData>>,
+    available: Vec<u32>,
+    // Free joint ranges as (offset, count), sorted by offset.
+    free_ranges: Vec<(u32, u32)>,
+    descriptors_dirty: bool,
+}
+
+/// `meshi_bindless_joints`: the skin matrix palette written by
+/// [`ReservedBindlessSkeletons`].
+pub struct ReservedBindlessJoints {
+    buffer: Handle<Buffer>,
+}
+
+impl ReservedBindlessSkeletons {
+    pub fn new(ctx: &mut Context) -> Self {
+        Self::with_capacity(ctx, SkeletonCapacity::default())
+    }
+
+    pub fn with_capacity(ctx: &mut Context, capacity: SkeletonCapacity) -> Self {
+        let skeleton_buffer = ctx
+            .make_buffer(&BufferInfo {
+                debug_name: "[FURIKAKE] Bindless Skeletons",
+                byte_size: capacity.skeletons * std::mem::size_of::<Skeleton>() as u32,
+                visibility: MemoryVisibility::CpuAndGpu,
+                usage: BufferUsage::STORAGE,
+                initial_data: None,
+            })
+            .expect("Failed making skeleton buffer");
+
+        let joint_buffer = ctx
+            .make_buffer(&BufferInfo {
+                debug_name: "[FURIKAKE] Bindless Joints",
+                byte_size: capacity.joints * std::mem::size_of::<Mat4>() as u32,
+                visibility: MemoryVisibility::CpuAndGpu,
+                usage: BufferUsage::STORAGE,
+                initial_data: None,
+            })
+            .expect("Failed making joint buffer");
+
+        Self {
+            capacity,
+            skeleton_buffer,
+            joint_buffer,
+            descriptors: Vec::new(),
+            skeletons: Vec::new(),
+            available: Vec::new(),
+            free_ranges: vec![(0, capacity.joints)],
+            descriptors_dirty: false,
+        }
+    }
+
+    pub fn joint_reservation(&self) -> ReservedBindlessJoints {
+        ReservedBindlessJoints {
+            buffer: self.joint_buffer,
+        }
+    }
+
+    /// Allocates a joint range sized to `inverse_bind`. Joint world transforms
+    /// start at the bind pose.
+    pub fn add_skeleton(
+        &mut self,
+        inverse_bind: &[Mat4],
+    ) -> Result<Handle<Skeleton>, FurikakeError> {
+        let full = || FurikakeError::ReservationFull {
+            name: "meshi_bindless_skeletons".to_string(),
+        };
+
+        if self.available.is_empty() && self.skeletons.len() >= self.capacity.skeletons as usize {
+            return Err(full());
+        }
+
+        let count = inverse_bind.len() as u32;
+        let range = self
+            .free_ranges
+            .iter()
+            .position(|(_, free)| *free >= count)
+            .ok_or_else(full)?;
+        let (offset, free) = self.free_ranges[range];
+        if free == count {
+            self.free_ranges.remove(range);
+        } else {
+            self.free_ranges[range] = (offset + count, free - count);
+        }
+
+        let slot = self.available.pop().unwrap_or_else(|| {
+            self.skeletons.push(None);
+            self.descriptors.push(Skeleton::default());
+            (self.skeletons.len() - 1) as u32
+        });
+
+        self.descriptors[slot as usize] = Skeleton {
+            joint_offset: offset,
+            joint_count: count,
+        };
+        self.skeletons[slot as usize] = Some(SkeletonData {
+            inverse_bind: inverse_bind.to_vec(),
+            joint_world: inverse_bind.iter().map(|m| m.inverse()).collect(),
+            skin: vec![Mat4::IDENTITY; inverse_bind.len()],
+            dirty: true,
+        });
+        self.descriptors_dirty = true;
+
+        super::slot_handle(slot).ok_or_else(full)
+    }
+
+    pub fn remove_skeleton(&mut self, skeleton: Handle<Skeleton>) {
+        let slot = skeleton.slot as usize;
+        if !skeleton.valid() || self.skeletons.get(slot).is_none_or(|s| s.is_none()) {
+            return;
+        }
+
+        let Skeleton {
+            joint_offset,
+            joint_count,
+        } = self.descriptors[slot];
+        self.release_range(joint_offset, joint_count);
+
+        self.skeletons[slot] = None;
+        self.descriptors[slot] = Skeleton::default();
+        self.available.push(u32::from(skeleton.slot));
+        self.descriptors_dirty = true;
+    }
+
+    pub fn skeleton(&self, handle: Handle<Skeleton>) -> &Skeleton {
+        &self.descriptors[handle.slot as usize]
+    }
+
+    /// Joint world transforms of a skeleton. Marks the skeleton dirty.
+    pub fn joint_world_mut(&mut self, handle: Handle<Skeleton>) -> &mut [Mat4] {
+        let data = self.data_mut(handle);
+        data.dirty = true;
+        &mut data.joint_world
+    }
+
+    /// Copies joint world transforms out of a scene graph, one node per joint.
+    pub fn set_joints_from_scene(
+        &mut self,
+        handle: Handle<Skeleton>,
+        scene: &SceneGraph,
+        joints: &[Handle<SceneNode>],
+    ) -> Result<(), FurikakeError> {
+        for (world, node) in self.joint_world_mut(handle).iter_mut().zip(joints) {
+            *world = scene.world(*node)?;
+        }
+        Ok(())
+    }
+
+    /// Skin matrices computed by the last `update()`.
+    pub fn skin_matrices(&self, handle: Handle<Skeleton>) -> &[Mat4] {
+        &self.skeletons[handle.slot as usize]
+            .as_ref()
+            .expect("skeleton handle was removed")
+            .skin
+    }
+
+    pub fn skeleton_buffer(&self) -> Handle<Buffer> {
+        self.skeleton_buffer
+    }
+
+    pub fn joint_buffer(&self) -> Handle<Buffer> {
+        self.joint_buffer
+    }
+
+    fn data_mut(&mut self, handle: Handle<Skeleton>) -> &mut SkeletonData {
+        self.skeletons[handle.slot as usize]
+            .as_mut()
+            .expect("skeleton handle was removed")
+    }
+
+    fn release_range(&mut self, offset: u32, count: u32) {
+        let index = self
+            .free_ranges
+            .partition_point(|(free_offset, _)| *free_offset < offset);
+        self.free_ranges.insert(index, (offset, count));
+
+        // Merge with the following range, then the preceding one.
+        if self
+            .free_ranges
+            .get(index + 1)
+            .is_some_and(|(next_offset, _)| offset + count == *next_offset)
+        {
+            let (_, next_count) = self.free_ranges.remove(index + 1);
+            self.free_ranges[index].1 += next_count;
+        }
+        if index > 0 {
+            let (prev_offset, prev_count) = self.free_ranges[index - 1];
+            if prev_offset + prev_count == offset {
+                self.free_ranges[index - 1].1 += self.free_ranges[index].1;
+                self.free_ranges.remove(index);
+            }
+        }
+    }
+}
+
+impl ReservedItem for ReservedBindlessSkeletons {
+    fn name(&self) -> String {
+        "meshi_bindless_skeletons".to_string()
+    }
+
+    fn update(&mut self, ctx: &mut Context) -> Result<(), FurikakeError> {
+        if self.descriptors_dirty {
+            let descriptors = ctx
+                .map_buffer_mut::<Skeleton>(self.skeleton_buffer)
+                .map_err(FurikakeError::buffer_map_failed)?;
+            descriptors[..self.descriptors.len()].copy_from_slice(&self.descriptors);
+            ctx.unmap_buffer(self.skeleton_buffer)
+                .map_err(FurikakeError::buffer_unmap_failed)?;
+            self.descriptors_dirty = false;
+        }
+
+        if !self.skeletons.iter().flatten().any(|data| data.dirty) {
+            return Ok(());
+        }
+
+        let joints = ctx
+            .map_buffer_mut::<Mat4>(self.joint_buffer)
+            .map_err(FurikakeError::buffer_map_failed)?;
+        for (descriptor, data) in self.descriptors.iter().zip(self.skeletons.iter_mut()) {
+            let Some(data) = data.as_mut().filter(|data| data.dirty) else {
+                continue;
+            };
+
+            for ((skin, world), inverse_bind) in data
+                .skin
+                .iter_mut()
+                .zip(&data.joint_world)
+                .zip(&data.inverse_bind)
+            {
+                *skin = *world * *inverse_bind;
+            }
+
+            let start = descriptor.joint_offset as usize;
+            joints[start..start + data.skin.len()].copy_from_slice(&data.skin);
+            data.dirty = false;
+        }
+        ctx.unmap_buffer(self.joint_buffer)
+            .map_err(FurikakeError::buffer_unmap_failed)?;
+
+        Ok(())
+    }
+
+    fn binding(&self) -> ReservedBinding<'_> {
+        ReservedBinding::Binding(BindingInfo {
+            resource: ShaderResource::StorageBuffer(self.skeleton_buffer),
+            binding: 0,
+        })
+    }
+
+    fn layout(&self) -> Option<ReservedLayout> {
+        Some(ReservedLayout::trailing_array(
+            StructLayout::default(),
+            "skeletons",
+            std::mem::size_of::<Skeleton>() as u32,
+        ))
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+impl ReservedItem for ReservedBindlessJoints {
+    fn name(&self) -> String {
+        "meshi_bindless_joints".to_string()
+    }
+
+    fn update(&mut self, _ctx: &mut Context) -> Result<(), FurikakeError> {
+        // Written by `ReservedBindlessSkeletons::update`.
+        Ok(())
+    }
+
+    fn binding(&self) -> ReservedBinding<'_> {
+        ReservedBinding::Binding(BindingInfo {
+            resource: ShaderResource::StorageBuffer(self.buffer),
+            binding: 0,
+        })
+    }
+
+    fn layout(&self) -> Option<ReservedLayout> {
+        Some(ReservedLayout::trailing_array(
+            StructLayout::default(),
+            "joints",
+            std::mem::size_of::<Mat4>() as u32,
+        ))
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dashi::ContextInfo;
+    use glam::Vec3;
+
+    #[test]
+    fn computes_skin_matrices() {
+        let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
+        let mut skeletons = ReservedBindlessSkeletons::new(&mut ctx);
+
+        let bind = [
+            Mat4::IDENTITY,
+            Mat4::from_translation(Vec3::new(0.0, 1.0, 0.0)),
+        ];
+        let inverse_bind: Vec<Mat4> = bind.iter().map(|m| m.inverse()).collect();
+        let handle = skeletons.add_skeleton(&inverse_bind).expect("add skeleton");
+
+        skeletons.update(&mut ctx).expect("update skeletons");
+        assert_eq!(skeletons.skin_matrices(handle), &[Mat4::IDENTITY; 2]);
+
+        skeletons.joint_world_mut(handle)[1] = Mat4::from_translation(Vec3::new(2.0, 1.0, 0.0));
+        skeletons.update(&mut ctx).expect("update skeletons");
+        assert_eq!(
+            skeletons.skin_matrices(handle)[1],
+            Mat4::from_translation(Vec3::new(2.0, 0.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn allocates_contiguous_joint_ranges() {
+        let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
+        let mut skeletons = ReservedBindlessSkeletons::with_capacity(
+            &mut ctx,
+            SkeletonCapacity {
+                joints: 8,
+                skeletons: 4,
+            },
+        );
+
+        let a = skeletons.add_skeleton(&[Mat4::IDENTITY; 3]).expect("add a");
+        let b = skeletons.add_skeleton(&[Mat4::IDENTITY; 3]).expect("add b");
+        assert_eq!(skeletons.skeleton(a).joint_offset, 0);
+        assert_eq!(skeletons.skeleton(b).joint_offset, 3);
+        assert!(skeletons.add_skeleton(&[Mat4::IDENTITY; 4]).is_err());
+
+        skeletons.remove_skeleton(a);
+        skeletons.remove_skeleton(b);
+        let c = skeletons
+            .add_skeleton(&[Mat4::IDENTITY; 8])
+            .expect("ranges merged");
+        assert_eq!(skeletons.skeleton(c).joint_offset, 0);
+        assert_eq!(skeletons.skeleton(c).joint_count, 8);
+    }
+}
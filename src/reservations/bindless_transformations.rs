@@ -1,117 +1,80 @@
 #![allow(dead_code)]
 
-use std::ptr::NonNull;
+use dashi::{Context, Handle};
+use glam::Mat4;
 
-use dashi::{BufferInfo, Context, Handle, IndexedBindingInfo, IndexedResource, ShaderResource};
+use crate::{
+    error::FurikakeError,
+    resolver::{ReservedLayout, ShaderLayout},
+    types::Transformation,
+};
 
-use crate::types::Transformation;
-
-use super::{ReservedBinding, ReservedItem};
+use super::{
+    ReservedBinding, ReservedItem,
+    bindless_pool::{BindlessMemory, BindlessPool, UploadStats},
+};
 
 pub struct ReservedBindlessTransformations {
-    ctx: NonNull<Context>,
-    device_transformation_data: Vec<IndexedResource>,
-    host_transformation_data: Vec<NonNull<Transformation>>,
-    available: Vec<u16>,
+    pool: BindlessPool<Transformation>,
+    // Model matrices as of the last update. `None` for slots that were just
+    // allocated, so their first previous transform is their current one.
+    last_transforms: Vec<Option<Mat4>>,
+    // Slots whose derived matrices may be stale: set when a record is handed
+    // out mutably and kept until its previous transform has caught up.
+    pending: Vec<bool>,
 }
 
 impl ReservedBindlessTransformations {
     pub fn new(ctx: &mut Context) -> Self {
-        const START_SIZE: usize = 512;
-
-        let mut d_data = Vec::with_capacity(START_SIZE);
-        let mut h_data = Vec::with_capacity(START_SIZE);
-        let available: Vec<u16> = (0..START_SIZE as u16).collect();
-
-        for i in 0..START_SIZE {
-            let default = [Transformation::default()];
-            let buf = ctx
-                .make_buffer(&BufferInfo {
-                    debug_name: &format!("[FURIKAKE] Bindless Transformation {}", i),
-                    byte_size: std::mem::size_of::<Transformation>() as u32,
-                    visibility: dashi::MemoryVisibility::CpuAndGpu,
-                    usage: dashi::BufferUsage::STORAGE,
-                    initial_data: Some(unsafe { default.align_to::<u8>().1 }),
-                })
-                .expect("Failed making transformation buffer");
-
-            let h = ctx
-                .map_buffer_mut::<Transformation>(buf)
-                .expect("Failed to map buffer");
-            let nnt =
-                NonNull::new(h.as_mut_ptr()).expect("NonNull failed check for transformation map!");
-
-            h_data.push(nnt);
-            d_data.push(IndexedResource {
-                resource: ShaderResource::StorageBuffer(buf),
-                slot: i as u32,
-            });
-        }
+        Self::with_memory(ctx, BindlessMemory::HostMapped)
+    }
 
+    pub fn with_memory(ctx: &mut Context, memory: BindlessMemory) -> Self {
+        let pool = BindlessPool::new(ctx, "Transformation", memory);
         Self {
-            ctx: NonNull::new(ctx).expect("NonNull failed check"),
-            device_transformation_data: d_data,
-            host_transformation_data: h_data,
-            available,
+            last_transforms: vec![None; pool.len()],
+            pending: vec![false; pool.len()],
+            pool,
         }
     }
 
     pub fn extend(&mut self) {
-        let ctx: &mut Context = unsafe { self.ctx.as_mut() };
-        if self.available.is_empty() {
-            const EXTENSION_SIZE: usize = 128;
-            let start = self.host_transformation_data.len();
-            let end = start + EXTENSION_SIZE;
-            for i in start..end {
-                let default = [Transformation::default()];
-                let buf = ctx
-                    .make_buffer(&BufferInfo {
-                        debug_name: &format!("[FURIKAKE] Bindless Transformation {}", i),
-                        byte_size: std::mem::size_of::<Transformation>() as u32,
-                        visibility: dashi::MemoryVisibility::CpuAndGpu,
-                        usage: dashi::BufferUsage::STORAGE,
-                        initial_data: Some(unsafe { default.align_to::<u8>().1 }),
-                    })
-                    .expect("Failed making transformation buffer");
-
-                let h = ctx
-                    .map_buffer_mut::<Transformation>(buf)
-                    .expect("Failed to map buffer");
-                let nnt = NonNull::new(h.as_mut_ptr())
-                    .expect("NonNull failed check for transformation map!");
-
-                self.host_transformation_data.push(nnt);
-                self.device_transformation_data.push(IndexedResource {
-                    resource: ShaderResource::StorageBuffer(buf),
-                    slot: i as u32,
-                });
-            }
-        }
+        self.pool.extend();
+        self.last_transforms.resize(self.pool.len(), None);
+        self.pending.resize(self.pool.len(), false);
     }
 
     pub fn remove_transformation(&mut self, transformation: Handle<Transformation>) {
-        if transformation.valid()
-            && (transformation.slot as usize) < self.device_transformation_data.len()
-        {
-            self.available.push(transformation.slot);
+        self.pool.remove(transformation);
+        if let Some(pending) = self.pending.get_mut(transformation.slot as usize) {
+            *pending = false;
         }
     }
 
-    pub fn add_transformation(&mut self) -> Handle<Transformation> {
-        if let Some(id) = self.available.pop() {
-            Handle::new(id, 0)
-        } else {
-            self.extend();
-            self.add_transformation()
-        }
+    pub fn add_transformation(&mut self) -> Result<Handle<Transformation>, FurikakeError> {
+        let handle = self
+            .pool
+            .add()
+            .ok_or_else(|| FurikakeError::ReservationFull { name: self.name() })?;
+        self.last_transforms.resize(self.pool.len(), None);
+        self.pending.resize(self.pool.len(), false);
+        self.last_transforms[handle.slot as usize] = None;
+        self.pending[handle.slot as usize] = true;
+        Ok(handle)
     }
 
     pub fn transformation(&self, handle: Handle<Transformation>) -> &Transformation {
-        unsafe { self.host_transformation_data[handle.slot as usize].as_ref() }
+        self.pool.get(handle.slot as usize)
     }
 
     pub fn transformation_mut(&mut self, handle: Handle<Transformation>) -> &mut Transformation {
-        unsafe { self.host_transformation_data[handle.slot as usize].as_mut() }
+        self.pending[handle.slot as usize] = true;
+        self.pool.get_mut(handle.slot as usize)
+    }
+
+    /// Bytes and slots uploaded by the last `update()` in device-local mode.
+    pub fn upload_stats(&self) -> UploadStats {
+        self.pool.upload_stats()
     }
 }
 
@@ -120,14 +83,48 @@ impl ReservedItem for ReservedBindlessTransformations {
         "meshi_bindless_transformations".to_string()
     }
 
-    fn update(&mut self, _ctx: &mut Context) -> Result<(), crate::error::FurikakeError> {
-        Ok(())
+    /// Refreshes the previous and normal matrices of slots handed out through
+    /// `transformation_mut()` since they last settled; untouched and free
+    /// slots are skipped.
+    fn update(&mut self, ctx: &mut Context) -> Result<(), crate::error::FurikakeError> {
+        for (slot, pending) in self.pending.iter_mut().enumerate() {
+            if !*pending {
+                continue;
+            }
+
+            let last = &mut self.last_transforms[slot];
+            let record = self.pool.get(slot);
+            let transform = record.transform;
+            let previous = last.unwrap_or(transform);
+            let normal = if *last == Some(transform) {
+                record.normal_matrix
+            } else {
+                record.compute_normal_matrix()
+            };
+
+            // Only touch records that change so device-local pools stay clean.
+            if record.previous_transform != previous || record.normal_matrix != normal {
+                let record = self.pool.get_mut(slot);
+                record.previous_transform = previous;
+                record.normal_matrix = normal;
+            }
+
+            *last = Some(transform);
+            // Stay pending until the previous transform catches up.
+            *pending = previous != transform;
+        }
+
+        self.pool.update(ctx)
     }
 
     fn binding(&self) -> ReservedBinding<'_> {
-        ReservedBinding::BindlessBinding(IndexedBindingInfo {
-            resources: &self.device_transformation_data,
-            binding: 0,
+        self.pool.binding()
+    }
+
+    fn layout(&self) -> Option<ReservedLayout> {
+        Some(ReservedLayout::Elements {
+            header: 0,
+            element: Transformation::shader_layout(),
         })
     }
 
@@ -144,19 +141,18 @@ impl ReservedItem for ReservedBindlessTransformations {
 mod tests {
     use super::*;
     use dashi::{Context, ContextInfo};
-    use glam::Mat4;
 
     #[test]
     fn reuses_transformation_slots() {
         let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
         let mut transformations = ReservedBindlessTransformations::new(&mut ctx);
 
-        let first = transformations.add_transformation();
-        let second = transformations.add_transformation();
+        let first = transformations.add_transformation().expect("free slot");
+        let second = transformations.add_transformation().expect("free slot");
         assert_ne!(first.slot, second.slot);
 
         transformations.remove_transformation(first);
-        let reused = transformations.add_transformation();
+        let reused = transformations.add_transformation().expect("free slot");
 
         assert_eq!(first.slot, reused.slot);
     }
@@ -166,7 +162,7 @@ mod tests {
         let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
         let mut transformations = ReservedBindlessTransformations::new(&mut ctx);
 
-        let handle = transformations.add_transformation();
+        let handle = transformations.add_transformation().expect("free slot");
         {
             let transform = transformations.transformation_mut(handle);
             transform.transform = Mat4::from_translation(glam::Vec3::new(1.0, 2.0, 3.0));
@@ -182,4 +178,131 @@ mod tests {
             Mat4::from_translation(glam::Vec3::new(1.0, 2.0, 3.0))
         );
     }
+
+    #[test]
+    fn maintains_normal_and_previous_matrices() {
+        let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
+        let mut transformations = ReservedBindlessTransformations::new(&mut ctx);
+
+        let handle = transformations.add_transformation().expect("free slot");
+        let first = Mat4::from_scale(glam::Vec3::new(2.0, 1.0, 1.0));
+        transformations.transformation_mut(handle).transform = first;
+        transformations
+            .update(&mut ctx)
+            .expect("update transformations");
+
+        let transform = transformations.transformation(handle);
+        assert_eq!(transform.previous_transform, first);
+        assert_eq!(
+            transform.normal_matrix,
+            Mat4::from_scale(glam::Vec3::new(0.5, 1.0, 1.0))
+        );
+
+        let second = Mat4::from_scale(glam::Vec3::splat(4.0));
+        transformations.transformation_mut(handle).transform = second;
+        transformations
+            .update(&mut ctx)
+            .expect("update transformations");
+
+        let transform = transformations.transformation(handle);
+        assert_eq!(transform.previous_transform, first);
+        assert_eq!(
+            transform.normal_matrix,
+            Mat4::from_scale(glam::Vec3::splat(0.25))
+        );
+
+        transformations
+            .update(&mut ctx)
+            .expect("update transformations");
+        assert_eq!(
+            transformations.transformation(handle).previous_transform,
+            second
+        );
+    }
+
+    #[test]
+    fn refreshes_only_written_slots() {
+        let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
+        let mut transformations = ReservedBindlessTransformations::new(&mut ctx);
+
+        let still = transformations.add_transformation().expect("free slot");
+        let moving = transformations.add_transformation().expect("free slot");
+        transformations
+            .update(&mut ctx)
+            .expect("update transformations");
+        assert!(!transformations.pending.contains(&true));
+
+        transformations.transformation_mut(moving).transform =
+            Mat4::from_translation(glam::Vec3::Z);
+        transformations
+            .update(&mut ctx)
+            .expect("update transformations");
+        assert!(!transformations.pending[still.slot as usize]);
+        assert!(transformations.pending[moving.slot as usize]);
+
+        transformations
+            .update(&mut ctx)
+            .expect("update transformations");
+        assert!(!transformations.pending.contains(&true));
+        assert_eq!(
+            transformations.transformation(moving).previous_transform,
+            Mat4::from_translation(glam::Vec3::Z)
+        );
+    }
+
+    #[test]
+    fn keeps_normal_matrices_for_small_scales() {
+        let tiny = Transformation {
+            transform: Mat4::from_scale(glam::Vec3::splat(1.0 / 256.0)),
+            ..Default::default()
+        };
+        assert_eq!(
+            tiny.compute_normal_matrix(),
+            Mat4::from_scale(glam::Vec3::splat(256.0))
+        );
+
+        let flat = Transformation {
+            transform: Mat4::from_scale(glam::Vec3::new(1.0, 0.0, 1.0)),
+            ..Default::default()
+        };
+        assert_eq!(flat.compute_normal_matrix(), Mat4::IDENTITY);
+    }
+
+    #[test]
+    fn device_local_uploads_only_changed_records() {
+        let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
+        let mut transformations =
+            ReservedBindlessTransformations::with_memory(&mut ctx, BindlessMemory::DeviceLocal);
+
+        let handle = transformations.add_transformation().expect("free slot");
+        transformations.transformation_mut(handle).transform =
+            Mat4::from_translation(glam::Vec3::X);
+        transformations
+            .update(&mut ctx)
+            .expect("update transformations");
+        assert_eq!(transformations.upload_stats().slots, 1);
+
+        transformations
+            .update(&mut ctx)
+            .expect("update transformations");
+        assert_eq!(transformations.upload_stats().slots, 0);
+
+        transformations.transformation_mut(handle).transform =
+            Mat4::from_translation(glam::Vec3::Y);
+        transformations
+            .update(&mut ctx)
+            .expect("update transformations");
+        assert_eq!(transformations.upload_stats().slots, 1);
+
+        // The previous transform catches up one frame after the move.
+        transformations
+            .update(&mut ctx)
+            .expect("update transformations");
+        assert_eq!(transformations.upload_stats().slots, 1);
+
+        transformations
+            .update(&mut ctx)
+            .expect("update transformations");
+        assert_eq!(transformations.upload_stats().slots, 0);
+    }
 }
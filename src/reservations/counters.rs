@@ -0,0 +1,186 @@
+use dashi::{
+    BindingInfo, Buffer, BufferInfo, BufferUsage, Context, Handle, MemoryVisibility, ShaderResource,
+};
+
+use crate::{
+    error::FurikakeError,
+    resolver::{ReservedLayout, StructLayout},
+};
+
+use super::{ReservedBinding, ReservedItem};
+
+/// Named statistics counters shaders bump with atomics:
+///
+/// ```glsl
+/// layout(set = N, binding = 0) buffer Counters {
+///     uint counters[];
+/// } meshi_counters;
+///
+/// atomicAdd(meshi_counters.counters[CULLED_INSTANCES], 1);
+/// ```
+///
+/// Indices come from `register_counter`. Each `update()` reads back the
+/// values accumulated since the previous one, then zeroes the buffer.
+pub struct ReservedCounters {
+    buffer: Handle<Buffer>,
+    capacity: u32,
+    names: Vec<String>,
+    last_values: Vec<u32>,
+    // The buffer starts with undefined contents, so the first update only clears.
+    primed: bool,
+}
+
+impl ReservedCounters {
+    const DEFAULT_CAPACITY: u32 = 256;
+
+    pub fn new(ctx: &mut Context) -> Self {
+        Self::with_capacity(ctx, Self::DEFAULT_CAPACITY)
+    }
+
+    pub fn with_capacity(ctx: &mut Context, capacity: u32) -> Self {
+        let buffer = ctx
+            .make_buffer(&BufferInfo {
+                debug_name: "[FURIKAKE] Counters",
+                byte_size: capacity * std::mem::size_of::<u32>() as u32,
+                visibility: MemoryVisibility::CpuAndGpu,
+                usage: BufferUsage::STORAGE,
+                initial_data: None,
+            })
+            .expect("Failed making counter buffer");
+
+        Self {
+            buffer,
+            capacity,
+            names: Vec::new(),
+            last_values: Vec::new(),
+            primed: false,
+        }
+    }
+
+    pub fn buffer(&self) -> Handle<Buffer> {
+        self.buffer
+    }
+
+    /// Registers a counter and returns the index shaders address it by.
+    /// Registering an existing name returns its index.
+    pub fn register_counter(&mut self, name: &str) -> Result<u32, FurikakeError> {
+        if let Some(index) = self.counter_index(name) {
+            return Ok(index);
+        }
+
+        if self.names.len() == self.capacity as usize {
+            return Err(FurikakeError::ReservationFull { name: self.name() });
+        }
+
+        self.names.push(name.to_string());
+        self.last_values.push(0);
+        Ok((self.names.len() - 1) as u32)
+    }
+
+    pub fn counter_index(&self, name: &str) -> Option<u32> {
+        self.names
+            .iter()
+            .position(|known| known == name)
+            .map(|index| index as u32)
+    }
+
+    /// Value the counter reached during the last completed frame.
+    pub fn value(&self, name: &str) -> Option<u32> {
+        let index = self.counter_index(name)?;
+        self.last_values.get(index as usize).copied()
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = (&str, u32)> {
+        self.names
+            .iter()
+            .map(String::as_str)
+            .zip(self.last_values.iter().copied())
+    }
+}
+
+impl ReservedItem for ReservedCounters {
+    fn name(&self) -> String {
+        "meshi_counters".to_string()
+    }
+
+    fn update(&mut self, ctx: &mut Context) -> Result<(), FurikakeError> {
+        let counters = ctx
+            .map_buffer_mut::<u32>(self.buffer)
+            .map_err(FurikakeError::buffer_map_failed)?;
+        if self.primed {
+            self.last_values
+                .copy_from_slice(&counters[..self.names.len()]);
+        }
+        counters[..self.capacity as usize].fill(0);
+        ctx.unmap_buffer(self.buffer)
+            .map_err(FurikakeError::buffer_unmap_failed)?;
+
+        self.primed = true;
+        Ok(())
+    }
+
+    fn binding(&self) -> ReservedBinding<'_> {
+        ReservedBinding::Binding(BindingInfo {
+            resource: ShaderResource::StorageBuffer(self.buffer),
+            binding: 0,
+        })
+    }
+
+    fn layout(&self) -> Option<ReservedLayout> {
+        Some(ReservedLayout::trailing_array(
+            StructLayout::default(),
+            "counters",
+            std::mem::size_of::<u32>() as u32,
+        ))
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reservations::write_as_shader;
+    use dashi::ContextInfo;
+
+    #[test]
+    fn reads_back_and_resets_counters() {
+        let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
+        let mut counters = ReservedCounters::with_capacity(&mut ctx, 2);
+
+        let culled = counters
+            .register_counter("culled_instances")
+            .expect("register");
+        let drawn = counters
+            .register_counter("drawn_triangles")
+            .expect("register");
+        assert_eq!(
+            counters.register_counter("culled_instances").ok(),
+            Some(culled)
+        );
+        assert!(matches!(
+            counters.register_counter("overdraw_samples"),
+            Err(FurikakeError::ReservationFull { .. })
+        ));
+        counters.update(&mut ctx).expect("clear counters");
+
+        write_as_shader::<u32>(&mut ctx, counters.buffer(), |mapped| {
+            mapped[culled as usize] += 12;
+            mapped[drawn as usize] += 3400;
+        });
+
+        counters.update(&mut ctx).expect("read back counters");
+        assert_eq!(counters.value("culled_instances"), Some(12));
+        assert_eq!(counters.value("drawn_triangles"), Some(3400));
+        assert_eq!(counters.value("overdraw_samples"), None);
+
+        counters.update(&mut ctx).expect("read back counters");
+        assert!(counters.values().all(|(_, value)| value == 0));
+    }
+}
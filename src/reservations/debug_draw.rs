@@ -0,0 +1,272 @@
+use dashi::{
+    BindingInfo, Buffer, BufferInfo, BufferUsage, Context, Handle, MemoryVisibility, ShaderResource,
+};
+use glam::{Vec3, Vec4};
+
+use crate::{
+    error::FurikakeError,
+    resolver::{ReservedLayout, ShaderLayout},
+};
+
+use super::{ReservedBinding, ReservedItem};
+
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DebugPrimitiveKind {
+    /// Segment from `a` to `b`.
+    Line = 0,
+    /// Axis-aligned box spanning `a` (min) to `b` (max).
+    Box = 1,
+}
+
+/// One shader-emitted primitive, std430 layout.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DebugPrimitive {
+    pub a: Vec3,
+    pub kind: u32,
+    pub b: Vec3,
+    pub _padding: u32,
+    pub color: Vec4,
+}
+
+crate::shader_layout!(DebugPrimitive {
+    a,
+    kind,
+    b,
+    _padding,
+    color
+});
+
+impl DebugPrimitive {
+    pub fn line(start: Vec3, end: Vec3, color: Vec4) -> Self {
+        Self {
+            a: start,
+            kind: DebugPrimitiveKind::Line as u32,
+            b: end,
+            _padding: 0,
+            color,
+        }
+    }
+
+    pub fn aabb(min: Vec3, max: Vec3, color: Vec4) -> Self {
+        Self {
+            a: min,
+            kind: DebugPrimitiveKind::Box as u32,
+            b: max,
+            _padding: 0,
+            color,
+        }
+    }
+
+    pub fn primitive_kind(&self) -> Option<DebugPrimitiveKind> {
+        match self.kind {
+            0 => Some(DebugPrimitiveKind::Line),
+            1 => Some(DebugPrimitiveKind::Box),
+            _ => None,
+        }
+    }
+
+    /// Line segments that draw this primitive; a box expands to its 12 edges.
+    pub fn segments(&self) -> Vec<(Vec3, Vec3)> {
+        match self.primitive_kind() {
+            Some(DebugPrimitiveKind::Line) => vec![(self.a, self.b)],
+            Some(DebugPrimitiveKind::Box) => {
+                let corner = |i: u32| {
+                    Vec3::select(
+                        glam::BVec3::new(i & 1 != 0, i & 2 != 0, i & 4 != 0),
+                        self.b,
+                        self.a,
+                    )
+                };
+                (0..8u32)
+                    .flat_map(|i| {
+                        [1, 2, 4]
+                            .into_iter()
+                            .filter(move |bit| i & bit == 0)
+                            .map(move |bit| (corner(i), corner(i | bit)))
+                    })
+                    .collect()
+            }
+            None => Vec::new(),
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct DebugDrawHeader {
+    primitive_count: u32,
+    capacity: u32,
+    _padding: [u32; 2],
+}
+
+/// Primitives read back from `meshi_debug_draw`.
+#[derive(Clone, Debug, Default)]
+pub struct DebugDrawReadback {
+    pub primitives: Vec<DebugPrimitive>,
+    /// Appends that did not fit in the buffer.
+    pub dropped: u32,
+}
+
+/// Append buffer shaders emit debug lines and boxes into:
+///
+/// ```glsl
+/// layout(set = N, binding = 0) buffer DebugDraw {
+///     uint primitive_count;
+///     uint capacity;
+///     DebugPrimitive primitives[];
+/// } meshi_debug_draw;
+///
+/// uint i = atomicAdd(meshi_debug_draw.primitive_count, 1);
+/// if (i < meshi_debug_draw.capacity) meshi_debug_draw.primitives[i] = p;
+/// ```
+///
+/// `update()` resets the counter, so read back after the frame's submission
+/// has completed and before the next `update()`.
+pub struct ReservedDebugDraw {
+    buffer: Handle<Buffer>,
+    capacity: u32,
+}
+
+impl ReservedDebugDraw {
+    const DEFAULT_CAPACITY: u32 = 16384;
+
+    pub fn new(ctx: &mut Context) -> Self {
+        Self::with_capacity(ctx, Self::DEFAULT_CAPACITY)
+    }
+
+    pub fn with_capacity(ctx: &mut Context, capacity: u32) -> Self {
+        let buffer = ctx
+            .make_buffer(&BufferInfo {
+                debug_name: "[FURIKAKE] Debug Draw",
+                byte_size: (std::mem::size_of::<DebugDrawHeader>()
+                    + capacity as usize * std::mem::size_of::<DebugPrimitive>())
+                    as u32,
+                visibility: MemoryVisibility::CpuAndGpu,
+                usage: BufferUsage::STORAGE,
+                initial_data: None,
+            })
+            .expect("Failed making debug draw buffer");
+
+        Self { buffer, capacity }
+    }
+
+    pub fn buffer(&self) -> Handle<Buffer> {
+        self.buffer
+    }
+
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    /// Copies out the primitives emitted since the last `update()`.
+    pub fn read_back(&self, ctx: &mut Context) -> Result<DebugDrawReadback, FurikakeError> {
+        let mapped = ctx
+            .map_buffer::<u8>(self.buffer)
+            .map_err(FurikakeError::buffer_map_failed)?;
+        let (header, body) = mapped.split_at(std::mem::size_of::<DebugDrawHeader>());
+        let header = unsafe { header.as_ptr().cast::<DebugDrawHeader>().read_unaligned() };
+        let count = header.primitive_count.min(self.capacity);
+        let primitives = body
+            .chunks_exact(std::mem::size_of::<DebugPrimitive>())
+            .take(count as usize)
+            .map(|bytes| unsafe { bytes.as_ptr().cast::<DebugPrimitive>().read_unaligned() })
+            .collect();
+        ctx.unmap_buffer(self.buffer)
+            .map_err(FurikakeError::buffer_unmap_failed)?;
+
+        Ok(DebugDrawReadback {
+            primitives,
+            dropped: header.primitive_count - count,
+        })
+    }
+}
+
+impl ReservedItem for ReservedDebugDraw {
+    fn name(&self) -> String {
+        "meshi_debug_draw".to_string()
+    }
+
+    fn update(&mut self, ctx: &mut Context) -> Result<(), FurikakeError> {
+        let header = ctx
+            .map_buffer_mut::<DebugDrawHeader>(self.buffer)
+            .map_err(FurikakeError::buffer_map_failed)?;
+        header[0] = DebugDrawHeader {
+            primitive_count: 0,
+            capacity: self.capacity,
+            ..Default::default()
+        };
+        ctx.unmap_buffer(self.buffer)
+            .map_err(FurikakeError::buffer_unmap_failed)?;
+
+        Ok(())
+    }
+
+    fn binding(&self) -> ReservedBinding<'_> {
+        ReservedBinding::Binding(BindingInfo {
+            resource: ShaderResource::StorageBuffer(self.buffer),
+            binding: 0,
+        })
+    }
+
+    fn layout(&self) -> Option<ReservedLayout> {
+        Some(ReservedLayout::Elements {
+            header: std::mem::size_of::<DebugDrawHeader>() as u32,
+            element: DebugPrimitive::shader_layout(),
+        })
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reservations::{record_bytes, write_as_shader};
+    use dashi::ContextInfo;
+
+    fn emit(ctx: &mut Context, debug: &ReservedDebugDraw, primitives: &[DebugPrimitive]) {
+        let capacity = debug.capacity() as usize;
+        write_as_shader::<u8>(ctx, debug.buffer(), |mapped| {
+            let header_size = std::mem::size_of::<DebugDrawHeader>();
+            let start = u32::from_ne_bytes(mapped[..4].try_into().expect("counter bytes"));
+            for (i, primitive) in primitives.iter().enumerate() {
+                let index = start as usize + i;
+                if index < capacity {
+                    let bytes = record_bytes(primitive);
+                    let offset = header_size + index * bytes.len();
+                    mapped[offset..offset + bytes.len()].copy_from_slice(bytes);
+                }
+            }
+            mapped[..4].copy_from_slice(&(start + primitives.len() as u32).to_ne_bytes());
+        });
+    }
+
+    #[test]
+    fn reads_back_and_resets_emitted_primitives() {
+        let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
+        let mut debug = ReservedDebugDraw::with_capacity(&mut ctx, 2);
+        debug.update(&mut ctx).expect("reset debug draw");
+
+        let line = DebugPrimitive::line(Vec3::ZERO, Vec3::X, Vec4::ONE);
+        let aabb = DebugPrimitive::aabb(Vec3::ZERO, Vec3::ONE, Vec4::new(1.0, 0.0, 0.0, 1.0));
+        emit(&mut ctx, &debug, &[line, aabb, line]);
+
+        let readback = debug.read_back(&mut ctx).expect("read back");
+        assert_eq!(readback.primitives, vec![line, aabb]);
+        assert_eq!(readback.dropped, 1);
+        assert_eq!(readback.primitives[1].segments().len(), 12);
+
+        debug.update(&mut ctx).expect("reset debug draw");
+        let readback = debug.read_back(&mut ctx).expect("read back");
+        assert!(readback.primitives.is_empty());
+        assert_eq!(readback.dropped, 0);
+    }
+}
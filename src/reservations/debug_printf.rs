@@ -0,0 +1,366 @@
+use std::fmt::Write;
+
+use dashi::{
+    BindingInfo, Buffer, BufferInfo, BufferUsage, Context, Handle, MemoryVisibility, ShaderResource,
+};
+
+use crate::{
+    error::FurikakeError,
+    resolver::{ReservedLayout, ShaderLayout},
+};
+
+use super::{ReservedBinding, ReservedItem};
+
+/// Values a single record can carry.
+pub const MAX_PRINTF_VALUES: usize = 6;
+
+/// One shader-written record, std430 layout. Values are raw 32-bit words;
+/// the format string decides how each is interpreted.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PrintfRecord {
+    pub format_id: u32,
+    pub value_count: u32,
+    pub values: [u32; MAX_PRINTF_VALUES],
+}
+
+crate::shader_layout!(PrintfRecord { format_id, value_count, values[u32] });
+
+/// Header in front of `records[]`. `PrintfRecord` only needs 4-byte
+/// alignment, so the records start right after it at offset 8.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct PrintfHeader {
+    head: u32,
+    capacity: u32,
+}
+
+/// A decoded record.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PrintfMessage {
+    pub format_id: u32,
+    pub values: Vec<u32>,
+    pub text: String,
+}
+
+/// Messages read back from `meshi_debug_printf`.
+#[derive(Clone, Debug, Default)]
+pub struct PrintfReadback {
+    pub messages: Vec<PrintfMessage>,
+    /// Records overwritten by the ring wrapping before they were read.
+    pub lost: u32,
+}
+
+/// Formats `values` with a printf-style `format`. Supports `%d`/`%i`
+/// (signed), `%u`, `%x`, `%f` and `%%`; missing values print as `?`.
+pub fn format_printf(format: &str, values: &[u32]) -> String {
+    let mut text = String::with_capacity(format.len());
+    let mut values = values.iter().copied();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            text.push(c);
+            continue;
+        }
+
+        let spec = chars.next();
+        if spec == Some('%') {
+            text.push('%');
+            continue;
+        }
+        let Some(value) = values.next() else {
+            text.push('?');
+            continue;
+        };
+        let _ = match spec {
+            Some('d' | 'i') => write!(text, "{}", value as i32),
+            Some('u') => write!(text, "{value}"),
+            Some('x') => write!(text, "{value:x}"),
+            Some('f') => write!(text, "{}", f32::from_bits(value)),
+            Some(other) => write!(text, "%{other}"),
+            None => write!(text, "%"),
+        };
+    }
+    text
+}
+
+/// Ring buffer shaders append printf records into:
+///
+/// ```glsl
+/// layout(set = N, binding = 0) buffer DebugPrintf {
+///     uint head;
+///     uint capacity;
+///     PrintfRecord records[];
+/// } meshi_debug_printf;
+///
+/// uint i = atomicAdd(meshi_debug_printf.head, 1) % meshi_debug_printf.capacity;
+/// meshi_debug_printf.records[i] = PrintfRecord(FORMAT_ID, 2, uint[6](a, floatBitsToUint(b), 0, 0, 0, 0));
+/// ```
+///
+/// Format ids come from `register_format`. `read_back` decodes every record
+/// written since the previous read.
+pub struct ReservedDebugPrintf {
+    buffer: Handle<Buffer>,
+    capacity: u32,
+    formats: Vec<String>,
+    read_head: u32,
+}
+
+impl ReservedDebugPrintf {
+    const DEFAULT_CAPACITY: u32 = 4096;
+
+    pub fn new(ctx: &mut Context) -> Self {
+        Self::with_capacity(ctx, Self::DEFAULT_CAPACITY)
+    }
+
+    pub fn with_capacity(ctx: &mut Context, capacity: u32) -> Self {
+        let buffer = ctx
+            .make_buffer(&BufferInfo {
+                debug_name: "[FURIKAKE] Debug Printf",
+                byte_size: (std::mem::size_of::<PrintfHeader>()
+                    + capacity as usize * std::mem::size_of::<PrintfRecord>())
+                    as u32,
+                visibility: MemoryVisibility::CpuAndGpu,
+                usage: BufferUsage::STORAGE,
+                initial_data: None,
+            })
+            .expect("Failed making debug printf buffer");
+
+        Self {
+            buffer,
+            capacity,
+            formats: Vec::new(),
+            read_head: 0,
+        }
+    }
+
+    pub fn buffer(&self) -> Handle<Buffer> {
+        self.buffer
+    }
+
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    /// Registers a format string and returns the id shaders write with it.
+    /// Registering the same string twice returns the same id.
+    pub fn register_format(&mut self, format: &str) -> u32 {
+        if let Some(id) = self.formats.iter().position(|known| known == format) {
+            return id as u32;
+        }
+        self.formats.push(format.to_string());
+        (self.formats.len() - 1) as u32
+    }
+
+    pub fn format(&self, format_id: u32) -> Option<&str> {
+        self.formats.get(format_id as usize).map(String::as_str)
+    }
+
+    pub fn decode(&self, record: &PrintfRecord) -> PrintfMessage {
+        let count = (record.value_count as usize).min(MAX_PRINTF_VALUES);
+        let values = record.values[..count].to_vec();
+        let text = match self.format(record.format_id) {
+            Some(format) => format_printf(format, &values),
+            None => format!("<unknown format {}> {values:?}", record.format_id),
+        };
+        PrintfMessage {
+            format_id: record.format_id,
+            values,
+            text,
+        }
+    }
+
+    /// Decodes the records written since the previous call. Call once the
+    /// frame's submission has completed.
+    pub fn read_back(&mut self, ctx: &mut Context) -> Result<PrintfReadback, FurikakeError> {
+        let mapped = ctx
+            .map_buffer::<u8>(self.buffer)
+            .map_err(FurikakeError::buffer_map_failed)?;
+        let (header, body) = mapped.split_at(std::mem::size_of::<PrintfHeader>());
+        let header = unsafe { header.as_ptr().cast::<PrintfHeader>().read_unaligned() };
+
+        let written = header.head.wrapping_sub(self.read_head);
+        let lost = written.saturating_sub(self.capacity);
+        let stride = std::mem::size_of::<PrintfRecord>();
+        let first = self.read_head.wrapping_add(lost);
+        let records: Vec<PrintfRecord> = (0..written - lost)
+            .map(|i| first.wrapping_add(i))
+            .map(|index| {
+                let offset = (index % self.capacity) as usize * stride;
+                unsafe {
+                    body[offset..offset + stride]
+                        .as_ptr()
+                        .cast::<PrintfRecord>()
+                        .read_unaligned()
+                }
+            })
+            .collect();
+        ctx.unmap_buffer(self.buffer)
+            .map_err(FurikakeError::buffer_unmap_failed)?;
+
+        self.read_head = header.head;
+        Ok(PrintfReadback {
+            messages: records.iter().map(|record| self.decode(record)).collect(),
+            lost,
+        })
+    }
+}
+
+impl ReservedItem for ReservedDebugPrintf {
+    fn name(&self) -> String {
+        "meshi_debug_printf".to_string()
+    }
+
+    fn update(&mut self, ctx: &mut Context) -> Result<(), FurikakeError> {
+        // The head keeps running so unread records survive the frame; only
+        // the capacity shaders wrap by needs to be published.
+        let header = ctx
+            .map_buffer_mut::<u32>(self.buffer)
+            .map_err(FurikakeError::buffer_map_failed)?;
+        header[1] = self.capacity;
+        ctx.unmap_buffer(self.buffer)
+            .map_err(FurikakeError::buffer_unmap_failed)?;
+
+        Ok(())
+    }
+
+    fn binding(&self) -> ReservedBinding<'_> {
+        ReservedBinding::Binding(BindingInfo {
+            resource: ShaderResource::StorageBuffer(self.buffer),
+            binding: 0,
+        })
+    }
+
+    fn layout(&self) -> Option<ReservedLayout> {
+        Some(ReservedLayout::Elements {
+            header: std::mem::size_of::<PrintfHeader>() as u32,
+            element: PrintfRecord::shader_layout(),
+        })
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reservations::{record_bytes, write_as_shader};
+    use crate::resolver::spirv::SpirvModule;
+    use dashi::ContextInfo;
+
+    fn emit(ctx: &mut Context, printf: &ReservedDebugPrintf, records: &[PrintfRecord]) {
+        let capacity = printf.capacity();
+        write_as_shader::<u8>(ctx, printf.buffer(), |mapped| {
+            let header_size = std::mem::size_of::<PrintfHeader>();
+            let mut head = u32::from_ne_bytes(mapped[..4].try_into().expect("head bytes"));
+            for record in records {
+                let bytes = record_bytes(record);
+                let offset = header_size + (head % capacity) as usize * bytes.len();
+                mapped[offset..offset + bytes.len()].copy_from_slice(bytes);
+                head += 1;
+            }
+            mapped[..4].copy_from_slice(&head.to_ne_bytes());
+        });
+    }
+
+    fn record(format_id: u32, values: &[u32]) -> PrintfRecord {
+        let mut record = PrintfRecord {
+            format_id,
+            value_count: values.len() as u32,
+            ..Default::default()
+        };
+        record.values[..values.len()].copy_from_slice(values);
+        record
+    }
+
+    #[test]
+    fn formats_printf_specifiers() {
+        let text = format_printf(
+            "cluster %u: %d lights, depth %f (%x) 100%% %u",
+            &[7, (-2i32) as u32, 1.5f32.to_bits(), 255],
+        );
+        assert_eq!(text, "cluster 7: -2 lights, depth 1.5 (ff) 100% ?");
+    }
+
+    #[test]
+    fn decodes_records_across_ring_wrap() {
+        let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
+        let mut printf = ReservedDebugPrintf::with_capacity(&mut ctx, 4);
+        printf.update(&mut ctx).expect("publish capacity");
+
+        let id = printf.register_format("instance %u visible");
+        assert_eq!(printf.register_format("instance %u visible"), id);
+
+        emit(&mut ctx, &printf, &[record(id, &[1]), record(id, &[2])]);
+        let readback = printf.read_back(&mut ctx).expect("read back");
+        let texts: Vec<_> = readback.messages.iter().map(|m| m.text.as_str()).collect();
+        assert_eq!(texts, ["instance 1 visible", "instance 2 visible"]);
+        assert_eq!(readback.lost, 0);
+
+        // Six records into a four-record ring: the oldest two are lost.
+        let batch: Vec<_> = (3..9).map(|i| record(id, &[i])).collect();
+        emit(&mut ctx, &printf, &batch);
+        let readback = printf.read_back(&mut ctx).expect("read back");
+        assert_eq!(readback.lost, 2);
+        let values: Vec<_> = readback.messages.iter().map(|m| m.values[0]).collect();
+        assert_eq!(values, [5, 6, 7, 8]);
+
+        assert!(
+            printf
+                .read_back(&mut ctx)
+                .expect("read back")
+                .messages
+                .is_empty()
+        );
+        assert!(
+            printf
+                .decode(&record(42, &[]))
+                .text
+                .starts_with("<unknown format 42>")
+        );
+    }
+
+    #[test]
+    fn header_matches_documented_block() {
+        let spirv: &[u32] = inline_spirv::inline_spirv!(
+            r#"
+            #version 450 core
+            layout(local_size_x = 1) in;
+
+            struct PrintfRecord {
+                uint format_id;
+                uint value_count;
+                uint values[6];
+            };
+            layout(set = 0, binding = 0) buffer DebugPrintf {
+                uint head;
+                uint capacity;
+                PrintfRecord records[];
+            } meshi_debug_printf;
+
+            void main() {
+                uint i = atomicAdd(meshi_debug_printf.head, 1) % meshi_debug_printf.capacity;
+                meshi_debug_printf.records[i] = PrintfRecord(1, 1, uint[6](i, 0, 0, 0, 0, 0));
+            }
+            "#,
+            comp
+        );
+
+        let module = SpirvModule::parse(spirv).expect("parse spirv");
+        let block = module
+            .variable_block("meshi_debug_printf")
+            .expect("printf block");
+        let records = block.block.member("records").expect("records member");
+        assert_eq!(records.offset as usize, std::mem::size_of::<PrintfHeader>());
+        assert_eq!(
+            block.elements.and_then(|(_, stride)| stride),
+            Some(std::mem::size_of::<PrintfRecord>() as u32)
+        );
+    }
+}
@@ -0,0 +1,216 @@
+use std::f32::consts::PI;
+
+use dashi::{
+    BindingInfo, Buffer, BufferInfo, BufferView, Context, Handle, MemoryVisibility, ShaderResource,
+};
+use glam::{Vec3, Vec4};
+
+use crate::{
+    error::FurikakeError,
+    resolver::{ReservedLayout, ShaderLayout},
+    types::Environment,
+};
+
+use super::{ReservedBinding, ReservedItem};
+
+/// Real spherical-harmonic basis for bands 0-2 evaluated at a unit direction,
+/// in the order `Y00, Y1-1, Y10, Y11, Y2-2, Y2-1, Y20, Y21, Y22`.
+pub fn sh_basis(dir: Vec3) -> [f32; 9] {
+    let Vec3 { x, y, z } = dir;
+    [
+        0.282_095,
+        0.488_603 * y,
+        0.488_603 * z,
+        0.488_603 * x,
+        1.092_548 * x * y,
+        1.092_548 * y * z,
+        0.315_392 * (3.0 * z * z - 1.0),
+        1.092_548 * x * z,
+        0.546_274 * (x * x - y * y),
+    ]
+}
+
+/// Projects an equirectangular HDR image (row-major, +Y up, top row at the
+/// zenith) into 9 radiance SH coefficients.
+pub fn project_equirectangular_sh(pixels: &[Vec3], width: usize, height: usize) -> [Vec3; 9] {
+    assert_eq!(
+        pixels.len(),
+        width * height,
+        "pixel count must be width * height"
+    );
+
+    let mut coefficients = [Vec3::ZERO; 9];
+    let texel_area = (2.0 * PI / width as f32) * (PI / height as f32);
+    for row in 0..height {
+        let theta = PI * (row as f32 + 0.5) / height as f32;
+        let solid_angle = texel_area * theta.sin();
+        for column in 0..width {
+            let phi = 2.0 * PI * (column as f32 + 0.5) / width as f32;
+            let dir = Vec3::new(
+                theta.sin() * phi.cos(),
+                theta.cos(),
+                theta.sin() * phi.sin(),
+            );
+            let radiance = pixels[row * width + column];
+            for (coefficient, basis) in coefficients.iter_mut().zip(sh_basis(dir)) {
+                *coefficient += radiance * basis * solid_angle;
+            }
+        }
+    }
+    coefficients
+}
+
+/// Convolves radiance SH with the clamped cosine lobe, giving irradiance SH.
+pub fn convolve_irradiance(radiance: [Vec3; 9]) -> [Vec3; 9] {
+    const BAND_SCALE: [f32; 3] = [PI, 2.0 * PI / 3.0, PI / 4.0];
+    let mut irradiance = radiance;
+    for (index, coefficient) in irradiance.iter_mut().enumerate() {
+        let band = match index {
+            0 => 0,
+            1..=3 => 1,
+            _ => 2,
+        };
+        *coefficient *= BAND_SCALE[band];
+    }
+    irradiance
+}
+
+/// Evaluates SH coefficients in direction `dir`.
+pub fn evaluate_sh(coefficients: &[Vec3; 9], dir: Vec3) -> Vec3 {
+    coefficients
+        .iter()
+        .zip(sh_basis(dir))
+        .map(|(coefficient, basis)| *coefficient * basis)
+        .sum()
+}
+
+pub struct ReservedEnvironment {
+    environment: Environment,
+    buffer: Handle<Buffer>,
+}
+
+impl ReservedEnvironment {
+    pub fn new(ctx: &mut Context) -> Self {
+        let buffer = ctx
+            .make_buffer(&BufferInfo {
+                debug_name: "[FURIKAKE] Environment Buffer",
+                byte_size: std::mem::size_of::<Environment>() as u32,
+                visibility: MemoryVisibility::CpuAndGpu,
+                ..Default::default()
+            })
+            .expect("Unable to make environment buffer!");
+
+        Self {
+            environment: Environment::default(),
+            buffer,
+        }
+    }
+
+    pub fn buffer(&self) -> Handle<Buffer> {
+        self.buffer
+    }
+
+    pub fn environment(&self) -> &Environment {
+        &self.environment
+    }
+
+    pub fn environment_mut(&mut self) -> &mut Environment {
+        &mut self.environment
+    }
+
+    /// Fills the irradiance SH from an equirectangular HDR image.
+    pub fn set_from_equirectangular(&mut self, pixels: &[Vec3], width: usize, height: usize) {
+        let irradiance = convolve_irradiance(project_equirectangular_sh(pixels, width, height));
+        for (packed, coefficient) in self.environment.irradiance_sh.iter_mut().zip(irradiance) {
+            *packed = coefficient.extend(0.0);
+        }
+    }
+
+    pub fn set_sun(&mut self, direction: Vec3, intensity: f32) {
+        self.environment.sun_direction = Vec4::from((direction.normalize(), intensity));
+    }
+}
+
+impl ReservedItem for ReservedEnvironment {
+    fn name(&self) -> String {
+        "meshi_environment".to_string()
+    }
+
+    fn update(&mut self, ctx: &mut Context) -> Result<(), FurikakeError> {
+        let s = ctx
+            .map_buffer_mut::<Environment>(self.buffer)
+            .map_err(FurikakeError::buffer_map_failed)?;
+        s[0] = self.environment;
+        ctx.unmap_buffer(self.buffer)
+            .map_err(FurikakeError::buffer_unmap_failed)?;
+
+        Ok(())
+    }
+
+    fn binding(&self) -> ReservedBinding<'_> {
+        ReservedBinding::Binding(BindingInfo {
+            resource: ShaderResource::ConstBuffer(BufferView {
+                handle: self.buffer,
+                size: std::mem::size_of::<Environment>() as u64,
+                offset: 0,
+            }),
+            binding: 0,
+        })
+    }
+
+    fn layout(&self) -> Option<ReservedLayout> {
+        Some(ReservedLayout::Block(Environment::shader_layout()))
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn projects_constant_environment() {
+        let color = Vec3::new(0.5, 1.0, 2.0);
+        let pixels = vec![color; 128 * 64];
+        let radiance = project_equirectangular_sh(&pixels, 128, 64);
+
+        assert!(radiance[0].abs_diff_eq(color * 0.282_095 * 4.0 * PI, 1e-2));
+        for coefficient in &radiance[1..] {
+            assert!(coefficient.abs_diff_eq(Vec3::ZERO, 1e-2));
+        }
+
+        for dir in [Vec3::X, Vec3::Y, Vec3::NEG_Z] {
+            assert!(evaluate_sh(&radiance, dir).abs_diff_eq(color, 1e-2));
+            // A constant sky of radiance L gives irradiance pi * L.
+            let irradiance = convolve_irradiance(radiance);
+            assert!(evaluate_sh(&irradiance, dir).abs_diff_eq(color * PI, 5e-2));
+        }
+    }
+
+    #[test]
+    fn captures_directional_gradient() {
+        // Bright upper hemisphere, dark lower hemisphere.
+        let (width, height) = (64, 32);
+        let pixels: Vec<Vec3> = (0..width * height)
+            .map(|i| {
+                if i / width < height / 2 {
+                    Vec3::ONE
+                } else {
+                    Vec3::ZERO
+                }
+            })
+            .collect();
+        let irradiance = convolve_irradiance(project_equirectangular_sh(&pixels, width, height));
+
+        let up = evaluate_sh(&irradiance, Vec3::Y).x;
+        let down = evaluate_sh(&irradiance, Vec3::NEG_Y).x;
+        assert!(up > down);
+    }
+}
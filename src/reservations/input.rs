@@ -0,0 +1,297 @@
+use dashi::{
+    BindingInfo, Buffer, BufferInfo, BufferView, Context, Handle, MemoryVisibility, ShaderResource,
+};
+use glam::{UVec4, Vec2};
+
+use crate::{
+    error::FurikakeError,
+    resolver::{ReservedLayout, ShaderLayout},
+};
+
+use super::{ReservedBinding, ReservedItem};
+
+/// Number of key codes tracked by the key bitmasks.
+pub const MAX_KEYS: u32 = 128;
+
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MouseButton {
+    Left = 0,
+    Right = 1,
+    Middle = 2,
+    Back = 3,
+    Forward = 4,
+}
+
+/// Input snapshot laid out for a std140 uniform:
+///
+/// ```glsl
+/// layout(set = N, binding = 0) uniform Input {
+///     vec2 mouse_position;
+///     vec2 mouse_delta;
+///     vec2 scroll;
+///     uint buttons_down;
+///     uint buttons_pressed;
+///     uint buttons_released;
+///     uvec4 keys_down;
+///     uvec4 keys_pressed;
+///     uvec4 keys_released;
+/// } meshi_input;
+/// ```
+///
+/// Key `k` lives in bit `k % 32` of component `k / 32`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct InputData {
+    pub mouse_position: Vec2,
+    pub mouse_delta: Vec2,
+    pub scroll: Vec2,
+    pub buttons_down: u32,
+    pub buttons_pressed: u32,
+    pub buttons_released: u32,
+    pub _padding: [u32; 3],
+    pub keys_down: UVec4,
+    pub keys_pressed: UVec4,
+    pub keys_released: UVec4,
+}
+
+crate::shader_layout!(InputData {
+    mouse_position,
+    mouse_delta,
+    scroll,
+    buttons_down,
+    buttons_pressed,
+    buttons_released,
+    _padding,
+    keys_down,
+    keys_pressed,
+    keys_released
+});
+
+/// Windowing-agnostic input state. The application forwards events from its
+/// window layer; key codes are whatever mapping the application picks, as
+/// long as they are below [`MAX_KEYS`].
+///
+/// A frame spans the events fed between two `update` calls. Presses and
+/// releases are latched as they arrive, so a tap shorter than a frame still
+/// shows up in both `pressed` and `released`.
+pub struct ReservedInput {
+    data: InputData,
+    buffer: Handle<Buffer>,
+    // False until the first `mouse_moved`, so the first delta is zero.
+    has_mouse_position: bool,
+}
+
+impl ReservedInput {
+    pub fn new(ctx: &mut Context) -> Self {
+        let buffer = ctx
+            .make_buffer(&BufferInfo {
+                debug_name: "[FURIKAKE] Input Buffer",
+                byte_size: std::mem::size_of::<InputData>() as u32,
+                visibility: MemoryVisibility::CpuAndGpu,
+                ..Default::default()
+            })
+            .expect("Unable to make input buffer!");
+
+        Self {
+            data: InputData::default(),
+            buffer,
+            has_mouse_position: false,
+        }
+    }
+
+    pub fn buffer(&self) -> Handle<Buffer> {
+        self.buffer
+    }
+
+    pub fn data(&self) -> &InputData {
+        &self.data
+    }
+
+    /// Records the cursor position. The first event only sets the position
+    /// and reports no movement.
+    pub fn mouse_moved(&mut self, position: Vec2) {
+        if self.has_mouse_position {
+            self.data.mouse_delta += position - self.data.mouse_position;
+        }
+        self.data.mouse_position = position;
+        self.has_mouse_position = true;
+    }
+
+    pub fn mouse_scrolled(&mut self, delta: Vec2) {
+        self.data.scroll += delta;
+    }
+
+    pub fn mouse_button(&mut self, button: MouseButton, down: bool) {
+        let bit = 1 << button as u32;
+        let InputData {
+            buttons_down,
+            buttons_pressed,
+            buttons_released,
+            ..
+        } = &mut self.data;
+        Self::latch(bit, down, buttons_down, buttons_pressed, buttons_released);
+    }
+
+    /// Records a key transition. Codes at or above [`MAX_KEYS`] are ignored.
+    pub fn key(&mut self, code: u32, down: bool) {
+        if code >= MAX_KEYS {
+            return;
+        }
+
+        let (word, bit) = ((code / 32) as usize, 1 << (code % 32));
+        let InputData {
+            keys_down,
+            keys_pressed,
+            keys_released,
+            ..
+        } = &mut self.data;
+        Self::latch(
+            bit,
+            down,
+            &mut keys_down[word],
+            &mut keys_pressed[word],
+            &mut keys_released[word],
+        );
+    }
+
+    pub fn is_button_down(&self, button: MouseButton) -> bool {
+        self.data.buttons_down & (1 << button as u32) != 0
+    }
+
+    pub fn button_pressed(&self, button: MouseButton) -> bool {
+        self.data.buttons_pressed & (1 << button as u32) != 0
+    }
+
+    pub fn button_released(&self, button: MouseButton) -> bool {
+        self.data.buttons_released & (1 << button as u32) != 0
+    }
+
+    pub fn is_key_down(&self, code: u32) -> bool {
+        Self::test_key(self.data.keys_down, code)
+    }
+
+    pub fn key_pressed(&self, code: u32) -> bool {
+        Self::test_key(self.data.keys_pressed, code)
+    }
+
+    pub fn key_released(&self, code: u32) -> bool {
+        Self::test_key(self.data.keys_released, code)
+    }
+
+    fn latch(bit: u32, down: bool, held: &mut u32, pressed: &mut u32, released: &mut u32) {
+        let was_down = *held & bit != 0;
+        if down && !was_down {
+            *held |= bit;
+            *pressed |= bit;
+        } else if !down && was_down {
+            *held &= !bit;
+            *released |= bit;
+        }
+    }
+
+    fn test_key(mask: UVec4, code: u32) -> bool {
+        code < MAX_KEYS && mask[(code / 32) as usize] & (1 << (code % 32)) != 0
+    }
+
+    /// Clears the per-frame edges and deltas once they have been uploaded.
+    fn end_frame(&mut self) {
+        self.data.mouse_delta = Vec2::ZERO;
+        self.data.scroll = Vec2::ZERO;
+        self.data.buttons_pressed = 0;
+        self.data.buttons_released = 0;
+        self.data.keys_pressed = UVec4::ZERO;
+        self.data.keys_released = UVec4::ZERO;
+    }
+}
+
+impl ReservedItem for ReservedInput {
+    fn name(&self) -> String {
+        "meshi_input".to_string()
+    }
+
+    fn update(&mut self, ctx: &mut Context) -> Result<(), FurikakeError> {
+        let s = ctx
+            .map_buffer_mut::<InputData>(self.buffer)
+            .map_err(FurikakeError::buffer_map_failed)?;
+        s[0] = self.data;
+        ctx.unmap_buffer(self.buffer)
+            .map_err(FurikakeError::buffer_unmap_failed)?;
+
+        self.end_frame();
+        Ok(())
+    }
+
+    fn binding(&self) -> ReservedBinding<'_> {
+        ReservedBinding::Binding(BindingInfo {
+            resource: ShaderResource::ConstBuffer(BufferView {
+                handle: self.buffer,
+                size: std::mem::size_of::<InputData>() as u64,
+                offset: 0,
+            }),
+            binding: 0,
+        })
+    }
+
+    fn layout(&self) -> Option<ReservedLayout> {
+        Some(ReservedLayout::Block(InputData::shader_layout()))
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dashi::ContextInfo;
+
+    #[test]
+    fn latches_edges_for_one_frame() {
+        let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
+        let mut input = ReservedInput::new(&mut ctx);
+
+        input.mouse_moved(Vec2::new(10.0, 20.0));
+        assert_eq!(input.data().mouse_delta, Vec2::ZERO);
+        input.mouse_moved(Vec2::new(15.0, 18.0));
+        input.mouse_scrolled(Vec2::new(0.0, 1.0));
+        input.mouse_button(MouseButton::Left, true);
+        input.key(65, true);
+        // A tap shorter than a frame reports both edges.
+        input.key(100, true);
+        input.key(100, false);
+
+        assert!(input.button_pressed(MouseButton::Left));
+        assert!(input.key_pressed(65) && input.is_key_down(65));
+        assert!(input.key_pressed(100) && input.key_released(100));
+        assert!(!input.is_key_down(100));
+        assert_eq!(input.data().mouse_delta, Vec2::new(5.0, -2.0));
+
+        input.update(&mut ctx).expect("update input");
+        let uploaded = ctx
+            .map_buffer::<InputData>(input.buffer())
+            .expect("map input buffer")[0];
+        assert_eq!(uploaded.keys_down[2], 1 << 1);
+        assert_eq!(uploaded.keys_released[3], 1 << 4);
+        assert_eq!(uploaded.buttons_down, 1);
+        assert_eq!(uploaded.scroll, Vec2::Y);
+        ctx.unmap_buffer(input.buffer())
+            .expect("unmap input buffer");
+
+        // Held state survives the frame boundary, edges and deltas do not.
+        assert!(input.is_button_down(MouseButton::Left));
+        assert!(!input.button_pressed(MouseButton::Left));
+        assert!(!input.key_pressed(65) && input.is_key_down(65));
+        assert_eq!(input.data().scroll, Vec2::ZERO);
+
+        input.mouse_button(MouseButton::Left, false);
+        assert!(input.button_released(MouseButton::Left));
+        input.key(MAX_KEYS, true);
+        assert!(!input.is_key_down(MAX_KEYS));
+    }
+}
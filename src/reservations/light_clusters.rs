@@ -0,0 +1,442 @@
+#![allow(dead_code)]
+
+use dashi::{
+    BindingInfo, Buffer, BufferInfo, BufferUsage, Context, Handle, MemoryVisibility, ShaderResource,
+};
+use glam::{UVec3, Vec3};
+
+use crate::{
+    error::FurikakeError,
+    resolver::{ReservedLayout, ShaderLayout, StructLayout},
+    types::{Camera, Light, LightKind},
+};
+
+use super::{ReservedBinding, ReservedItem};
+
+/// Froxel grid laid over a camera's view frustum. Depth slices are spaced
+/// exponentially between `near` and `far`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClusterGrid {
+    pub dims: UVec3,
+    pub near: f32,
+    pub far: f32,
+    /// Vertical field of view in radians.
+    pub fov_y: f32,
+    pub aspect: f32,
+    pub max_lights_per_cluster: u32,
+}
+
+impl Default for ClusterGrid {
+    fn default() -> Self {
+        Self {
+            dims: UVec3::new(16, 9, 24),
+            near: 0.1,
+            far: 1000.0,
+            fov_y: std::f32::consts::FRAC_PI_3,
+            aspect: 16.0 / 9.0,
+            max_lights_per_cluster: 64,
+        }
+    }
+}
+
+impl ClusterGrid {
+    pub fn cluster_count(&self) -> usize {
+        (self.dims.x * self.dims.y * self.dims.z) as usize
+    }
+
+    pub fn cluster_index(&self, x: u32, y: u32, z: u32) -> usize {
+        (x + self.dims.x * (y + self.dims.y * z)) as usize
+    }
+
+    /// View distance of the near plane of depth slice `slice`.
+    pub fn slice_depth(&self, slice: u32) -> f32 {
+        self.near * (self.far / self.near).powf(slice as f32 / self.dims.z as f32)
+    }
+
+    /// `(scale, bias)` such that `floor(log(depth) * scale + bias)` is the
+    /// depth slice of a view distance.
+    pub fn slice_scale_bias(&self) -> (f32, f32) {
+        let log_range = (self.far / self.near).ln();
+        let scale = self.dims.z as f32 / log_range;
+        (scale, -(self.dims.z as f32) * self.near.ln() / log_range)
+    }
+
+    /// View-space bounds of a cluster. View space looks down -Z.
+    pub fn cluster_bounds(&self, x: u32, y: u32, z: u32) -> (Vec3, Vec3) {
+        let tan_y = (self.fov_y * 0.5).tan();
+        let tan_x = tan_y * self.aspect;
+        let ndc = |i: u32, n: u32| -1.0 + 2.0 * i as f32 / n as f32;
+
+        let mut min = Vec3::splat(f32::MAX);
+        let mut max = Vec3::splat(f32::MIN);
+        for depth in [self.slice_depth(z), self.slice_depth(z + 1)] {
+            for nx in [ndc(x, self.dims.x), ndc(x + 1, self.dims.x)] {
+                for ny in [ndc(y, self.dims.y), ndc(y + 1, self.dims.y)] {
+                    let corner = Vec3::new(nx * tan_x * depth, ny * tan_y * depth, -depth);
+                    min = min.min(corner);
+                    max = max.max(corner);
+                }
+            }
+        }
+        (min, max)
+    }
+}
+
+/// Offset and count into the cluster light index list.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ClusterRange {
+    pub offset: u32,
+    pub count: u32,
+}
+
+/// Result of assigning lights to clusters.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ClusterAssignment {
+    pub clusters: Vec<ClusterRange>,
+    /// Dense indices into `meshi_bindless_lights.lights`.
+    pub light_indices: Vec<u32>,
+    /// Light references dropped because a cluster was full.
+    pub overflowed: u32,
+}
+
+/// CPU reference implementation of clustered light assignment. Lights are
+/// bounded by a sphere of radius `range`; directional lights and lights with a
+/// non-positive range touch every cluster.
+pub fn assign_lights(grid: &ClusterGrid, camera: &Camera, lights: &[Light]) -> ClusterAssignment {
+    let view = camera.view_matrix();
+    let spheres: Vec<Option<(Vec3, f32)>> = lights
+        .iter()
+        .map(|light| {
+            if light.light_kind() == LightKind::Directional || light.range <= 0.0 {
+                None
+            } else {
+                Some((view.transform_point3(light.position), light.range))
+            }
+        })
+        .collect();
+
+    let mut assignment = ClusterAssignment {
+        clusters: Vec::with_capacity(grid.cluster_count()),
+        ..Default::default()
+    };
+
+    for z in 0..grid.dims.z {
+        for y in 0..grid.dims.y {
+            for x in 0..grid.dims.x {
+                let (min, max) = grid.cluster_bounds(x, y, z);
+                let offset = assignment.light_indices.len() as u32;
+                let mut count = 0;
+                for (index, sphere) in spheres.iter().enumerate() {
+                    let touches = match sphere {
+                        None => true,
+                        Some((center, radius)) => {
+                            center.clamp(min, max).distance_squared(*center) <= radius * radius
+                        }
+                    };
+
+                    if !touches {
+                        continue;
+                    }
+
+                    if count == grid.max_lights_per_cluster {
+                        assignment.overflowed += 1;
+                        continue;
+                    }
+
+                    assignment.light_indices.push(index as u32);
+                    count += 1;
+                }
+
+                assignment.clusters.push(ClusterRange { offset, count });
+            }
+        }
+    }
+
+    assignment
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct ClusterHeader {
+    dims: [u32; 4],
+    depth: [f32; 4],
+}
+
+crate::shader_layout!(ClusterHeader { dims, depth });
+
+/// Cluster grid and per-cluster light ranges:
+///
+/// ```glsl
+/// layout(set = N, binding = 0) buffer LightClusters {
+///     uvec4 dims;       // x, y, z, max lights per cluster
+///     vec4 depth;       // near, far, slice scale, slice bias
+///     uvec2 clusters[]; // offset, count into meshi_light_cluster_indices
+/// } meshi_light_clusters;
+/// ```
+///
+/// The index list lives in a second buffer bound through
+/// [`ReservedLightClusterIndices`].
+pub struct ReservedLightClusters {
+    grid: ClusterGrid,
+    assignment: ClusterAssignment,
+    cluster_buffer: Handle<Buffer>,
+    index_buffer: Handle<Buffer>,
+}
+
+/// `meshi_light_cluster_indices`: `uint light_indices[]` referenced by the
+/// ranges in `meshi_light_clusters`.
+pub struct ReservedLightClusterIndices {
+    buffer: Handle<Buffer>,
+}
+
+impl ReservedLightClusters {
+    pub fn new(ctx: &mut Context, grid: ClusterGrid) -> Self {
+        let cluster_buffer = ctx
+            .make_buffer(&BufferInfo {
+                debug_name: "[FURIKAKE] Light Clusters",
+                byte_size: (std::mem::size_of::<ClusterHeader>()
+                    + grid.cluster_count() * std::mem::size_of::<ClusterRange>())
+                    as u32,
+                visibility: MemoryVisibility::CpuAndGpu,
+                usage: BufferUsage::STORAGE,
+                initial_data: None,
+            })
+            .expect("Failed making light cluster buffer");
+
+        let index_buffer = ctx
+            .make_buffer(&BufferInfo {
+                debug_name: "[FURIKAKE] Light Cluster Indices",
+                byte_size: (grid.cluster_count()
+                    * grid.max_lights_per_cluster as usize
+                    * std::mem::size_of::<u32>()) as u32,
+                visibility: MemoryVisibility::CpuAndGpu,
+                usage: BufferUsage::STORAGE,
+                initial_data: None,
+            })
+            .expect("Failed making light cluster index buffer");
+
+        Self {
+            grid,
+            assignment: ClusterAssignment {
+                clusters: vec![ClusterRange::default(); grid.cluster_count()],
+                ..Default::default()
+            },
+            cluster_buffer,
+            index_buffer,
+        }
+    }
+
+    /// The companion reservation exposing the light index list.
+    pub fn index_reservation(&self) -> ReservedLightClusterIndices {
+        ReservedLightClusterIndices {
+            buffer: self.index_buffer,
+        }
+    }
+
+    pub fn grid(&self) -> &ClusterGrid {
+        &self.grid
+    }
+
+    /// Updates the projection used to build clusters. The grid dimensions and
+    /// per-cluster budget are fixed at creation since they size the buffers.
+    pub fn set_projection(&mut self, near: f32, far: f32, fov_y: f32, aspect: f32) {
+        self.grid.near = near;
+        self.grid.far = far;
+        self.grid.fov_y = fov_y;
+        self.grid.aspect = aspect;
+    }
+
+    pub fn assignment(&self) -> &ClusterAssignment {
+        &self.assignment
+    }
+
+    /// Runs [`assign_lights`] and stores the result for the next `update()`.
+    pub fn assign(&mut self, camera: &Camera, lights: &[Light]) {
+        self.assignment = assign_lights(&self.grid, camera, lights);
+    }
+
+    pub fn cluster_buffer(&self) -> Handle<Buffer> {
+        self.cluster_buffer
+    }
+
+    pub fn index_buffer(&self) -> Handle<Buffer> {
+        self.index_buffer
+    }
+}
+
+impl ReservedItem for ReservedLightClusters {
+    fn name(&self) -> String {
+        "meshi_light_clusters".to_string()
+    }
+
+    fn update(&mut self, ctx: &mut Context) -> Result<(), FurikakeError> {
+        let (scale, bias) = self.grid.slice_scale_bias();
+        let header = [ClusterHeader {
+            dims: [
+                self.grid.dims.x,
+                self.grid.dims.y,
+                self.grid.dims.z,
+                self.grid.max_lights_per_cluster,
+            ],
+            depth: [self.grid.near, self.grid.far, scale, bias],
+        }];
+        let header_bytes = unsafe { header.align_to::<u8>().1 };
+        let range_bytes = unsafe { self.assignment.clusters.align_to::<u8>().1 };
+
+        let mapped = ctx
+            .map_buffer_mut::<u8>(self.cluster_buffer)
+            .map_err(FurikakeError::buffer_map_failed)?;
+        mapped[..header_bytes.len()].copy_from_slice(header_bytes);
+        mapped[header_bytes.len()..header_bytes.len() + range_bytes.len()]
+            .copy_from_slice(range_bytes);
+        ctx.unmap_buffer(self.cluster_buffer)
+            .map_err(FurikakeError::buffer_unmap_failed)?;
+
+        let indices = ctx
+            .map_buffer_mut::<u32>(self.index_buffer)
+            .map_err(FurikakeError::buffer_map_failed)?;
+        indices[..self.assignment.light_indices.len()]
+            .copy_from_slice(&self.assignment.light_indices);
+        ctx.unmap_buffer(self.index_buffer)
+            .map_err(FurikakeError::buffer_unmap_failed)?;
+
+        Ok(())
+    }
+
+    fn binding(&self) -> ReservedBinding<'_> {
+        ReservedBinding::Binding(BindingInfo {
+            resource: ShaderResource::StorageBuffer(self.cluster_buffer),
+            binding: 0,
+        })
+    }
+
+    fn layout(&self) -> Option<ReservedLayout> {
+        Some(ReservedLayout::trailing_array(
+            ClusterHeader::shader_layout(),
+            "clusters",
+            std::mem::size_of::<ClusterRange>() as u32,
+        ))
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+impl ReservedItem for ReservedLightClusterIndices {
+    fn name(&self) -> String {
+        "meshi_light_cluster_indices".to_string()
+    }
+
+    fn update(&mut self, _ctx: &mut Context) -> Result<(), FurikakeError> {
+        // Written by `ReservedLightClusters::update`.
+        Ok(())
+    }
+
+    fn binding(&self) -> ReservedBinding<'_> {
+        ReservedBinding::Binding(BindingInfo {
+            resource: ShaderResource::StorageBuffer(self.buffer),
+            binding: 0,
+        })
+    }
+
+    fn layout(&self) -> Option<ReservedLayout> {
+        Some(ReservedLayout::trailing_array(
+            StructLayout::default(),
+            "light_indices",
+            std::mem::size_of::<u32>() as u32,
+        ))
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Quat;
+
+    fn grid() -> ClusterGrid {
+        ClusterGrid {
+            dims: UVec3::new(4, 4, 8),
+            near: 1.0,
+            far: 100.0,
+            fov_y: std::f32::consts::FRAC_PI_2,
+            aspect: 1.0,
+            max_lights_per_cluster: 8,
+        }
+    }
+
+    #[test]
+    fn slices_cover_depth_range() {
+        let grid = grid();
+        assert!((grid.slice_depth(0) - grid.near).abs() < 1e-4);
+        assert!((grid.slice_depth(grid.dims.z) - grid.far).abs() < 1e-3);
+
+        let (scale, bias) = grid.slice_scale_bias();
+        let depth = grid.slice_depth(3) * 1.01;
+        assert_eq!((depth.ln() * scale + bias).floor() as u32, 3);
+    }
+
+    #[test]
+    fn assigns_lights_to_overlapping_clusters() {
+        let grid = grid();
+        let camera = Camera::new(Vec3::ZERO, Quat::IDENTITY);
+        let lights = [
+            Light::directional(Vec3::NEG_Y, Vec3::ONE, 1.0),
+            Light::point(Vec3::new(0.0, 0.0, -10.0), Vec3::ONE, 1.0, 0.5),
+        ];
+
+        let assignment = assign_lights(&grid, &camera, &lights);
+        assert_eq!(assignment.clusters.len(), grid.cluster_count());
+        assert_eq!(assignment.overflowed, 0);
+
+        let (scale, bias) = grid.slice_scale_bias();
+        let slice = (10.0_f32.ln() * scale + bias).floor() as u32;
+        let mut touched = 0;
+        for z in 0..grid.dims.z {
+            for y in 0..grid.dims.y {
+                for x in 0..grid.dims.x {
+                    let range = assignment.clusters[grid.cluster_index(x, y, z)];
+                    let indices = &assignment.light_indices
+                        [range.offset as usize..(range.offset + range.count) as usize];
+                    assert_eq!(indices[0], 0, "directional light reaches every cluster");
+                    if indices.contains(&1) {
+                        touched += 1;
+                        assert!(z.abs_diff(slice) <= 1);
+                        assert!((1..=2).contains(&x) && (1..=2).contains(&y));
+                    }
+                }
+            }
+        }
+        assert!(touched > 0);
+    }
+
+    #[test]
+    fn reports_cluster_overflow() {
+        let grid = ClusterGrid {
+            max_lights_per_cluster: 1,
+            ..grid()
+        };
+        let camera = Camera::default();
+        let lights = [
+            Light::directional(Vec3::NEG_Y, Vec3::ONE, 1.0),
+            Light::directional(Vec3::NEG_X, Vec3::ONE, 1.0),
+        ];
+
+        let assignment = assign_lights(&grid, &camera, &lights);
+        assert_eq!(assignment.light_indices.len(), grid.cluster_count());
+        assert_eq!(assignment.overflowed as usize, grid.cluster_count());
+    }
+}
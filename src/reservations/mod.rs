@@ -1,17 +1,36 @@
+pub mod bindless_bounds;
 pub mod bindless_camera;
+pub mod bindless_geometry;
+pub mod bindless_instances;
+pub mod bindless_lights;
 pub mod bindless_materials;
+pub mod bindless_pool;
+pub mod bindless_skeletons;
 pub mod bindless_textures;
 pub mod bindless_transformations;
 pub mod camera;
+pub mod counters;
+pub mod debug_draw;
+pub mod debug_printf;
+pub mod environment;
+pub mod input;
+pub mod light_clusters;
+pub mod particles;
+pub mod push_constants;
+pub mod random;
 pub mod timing;
+pub mod viewport;
+pub use bindless_pool::{BindlessMemory, UploadStats};
 pub use timing::*;
 
-use dashi::{BindingInfo, Context, IndexedBindingInfo};
+use dashi::{BindingInfo, Context, Handle, IndexedBindingInfo};
 use std::any::Any;
 
 pub enum ReservedBinding<'a> {
     Binding(BindingInfo),
     BindlessBinding(IndexedBindingInfo<'a>),
+    /// Values recorded per draw into a push-constant block.
+    PushConstant(&'a push_constants::PushConstants),
 }
 
 pub trait ReservedItem {
@@ -19,6 +38,44 @@ pub trait ReservedItem {
     fn update(&mut self, ctx: &mut Context) -> Result<(), crate::error::FurikakeError>;
     fn binding(&self) -> ReservedBinding<'_>;
 
+    /// Rust layout of the data this reservation writes, checked against the
+    /// shader's declaration by the resolver. `None` skips the check.
+    fn layout(&self) -> Option<crate::resolver::ReservedLayout> {
+        None
+    }
+
     fn as_any(&self) -> &dyn Any;
     fn as_any_mut(&mut self) -> &mut dyn Any;
 }
+
+/// Most slots a reservation can hand out handles for. Handles are dashi's,
+/// which carry a 16-bit slot, so every pool stops at 65,536 entries and
+/// reports `ReservationFull` past that. Texture ids and pool indices are `u32`
+/// so they match the `uint` fields shaders declare; they do not raise this cap.
+pub const MAX_SLOTS: usize = u16::MAX as usize + 1;
+
+/// Converts a pool index into the dashi handle returned to callers. Indices
+/// past [`MAX_SLOTS`] have no handle and callers report the reservation full.
+pub(crate) fn slot_handle<T>(index: u32) -> Option<Handle<T>> {
+    u16::try_from(index).ok().map(|slot| Handle::new(slot, 0))
+}
+
+/// Maps `buffer` and hands it to `write`. Headless test contexts have no
+/// pipeline to run, so tests use this to stand in for shader-side writes
+/// such as atomic appends before reading a reservation back.
+#[cfg(test)]
+pub(crate) fn write_as_shader<T>(
+    ctx: &mut Context,
+    buffer: Handle<dashi::Buffer>,
+    write: impl FnOnce(&mut [T]),
+) {
+    let mapped = ctx.map_buffer_mut::<T>(buffer).expect("map buffer");
+    write(mapped);
+    ctx.unmap_buffer(buffer).expect("unmap buffer");
+}
+
+/// Raw bytes of a `#[repr(C)]` record, for writes through [`write_as_shader`].
+#[cfg(test)]
+pub(crate) fn record_bytes<T>(record: &T) -> &[u8] {
+    unsafe { std::slice::from_raw_parts((record as *const T).cast(), std::mem::size_of::<T>()) }
+}
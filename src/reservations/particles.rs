@@ -0,0 +1,616 @@
+use dashi::{
+    BindingInfo, Buffer, BufferInfo, BufferUsage, Context, Handle, MemoryVisibility, ShaderResource,
+};
+
+use crate::{
+    error::FurikakeError,
+    resolver::{ReservedLayout, ShaderLayout},
+    types::{DispatchIndirectCommand, Particle, ParticleEmitter},
+};
+
+use super::{ReservedBinding, ReservedItem, slot_handle};
+
+/// Local size the dispatch arguments are computed for.
+pub const PARTICLE_GROUP_SIZE: u32 = 64;
+
+/// Index of the emission dispatch in `meshi_particle_dispatch`.
+pub const EMIT_DISPATCH: usize = 0;
+/// Index of the simulation dispatch in `meshi_particle_dispatch`.
+pub const SIMULATE_DISPATCH: usize = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParticleCapacity {
+    pub particles: u32,
+    pub emitters: u32,
+}
+
+impl Default for ParticleCapacity {
+    fn default() -> Self {
+        Self {
+            particles: 65536,
+            emitters: 256,
+        }
+    }
+}
+
+/// Header of `meshi_particle_lists`. `dead_count` and `alive_count` are
+/// maintained by shaders; `current` flips every frame. `indices[]` follows
+/// at offset 20.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct ParticleListHeader {
+    dead_count: u32,
+    alive_count: [u32; 2],
+    current: u32,
+    capacity: u32,
+}
+
+crate::shader_layout!(ParticleListHeader {
+    dead_count,
+    alive_count[u32],
+    current,
+    capacity
+});
+
+/// Header of `meshi_particle_emitters`. `ParticleEmitter` holds vec3s, so
+/// `emitters[]` is 16-byte aligned and starts at offset 16.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct EmitterHeader {
+    emitter_count: u32,
+    _padding: [u32; 3],
+}
+
+/// GPU particle pool for compute simulation, exposed through four names:
+///
+/// ```glsl
+/// layout(set = N, binding = 0) buffer Particles {
+///     Particle particles[];
+/// } meshi_particles;
+///
+/// // indices[0, capacity) is the dead list, followed by two alive lists.
+/// // Simulation reads alive list `current` and appends survivors and new
+/// // particles to alive list `current ^ 1`.
+/// layout(set = N, binding = 0) buffer ParticleLists {
+///     uint dead_count;
+///     uint alive_count[2];
+///     uint current;
+///     uint capacity;
+///     uint indices[];
+/// } meshi_particle_lists;
+///
+/// layout(set = N, binding = 0) buffer ParticleEmitters {
+///     uint emitter_count;
+///     ParticleEmitter emitters[];
+/// } meshi_particle_emitters;
+///
+/// // [EMIT_DISPATCH, SIMULATE_DISPATCH] as VkDispatchIndirectCommand.
+/// meshi_particle_dispatch
+/// ```
+///
+/// Emitter handles index `emitters[]` directly, so live particles keep a valid
+/// `emitter_index` after their emitter is removed. The slot is only reused
+/// once the emitter's `lifetime` has passed and its last particle expired.
+pub struct ReservedParticles {
+    capacity: ParticleCapacity,
+    particle_buffer: Handle<Buffer>,
+    list_buffer: Handle<Buffer>,
+    emitter_buffer: Handle<Buffer>,
+    dispatch_buffer: Handle<Buffer>,
+    emitters: Vec<ParticleEmitter>,
+    live: Vec<bool>,
+    available: Vec<u32>,
+    // Removed emitter slots with the seconds left until their particles expire.
+    retired: Vec<(u32, f32)>,
+    // Fractional particles carried between frames for each emitter.
+    spawn_remainder: Vec<f32>,
+    // The list buffer starts undefined; the first update fills the dead list.
+    primed: bool,
+    current: u32,
+}
+
+/// A particle buffer exposed under its own reserved name.
+pub struct ReservedParticleBuffer {
+    name: &'static str,
+    buffer: Handle<Buffer>,
+    layout: Option<ReservedLayout>,
+}
+
+impl ReservedParticles {
+    pub fn new(ctx: &mut Context) -> Self {
+        Self::with_capacity(ctx, ParticleCapacity::default())
+    }
+
+    pub fn with_capacity(ctx: &mut Context, capacity: ParticleCapacity) -> Self {
+        let mut make = |debug_name: &str, byte_size: usize, visibility: MemoryVisibility| {
+            ctx.make_buffer(&BufferInfo {
+                debug_name,
+                byte_size: byte_size as u32,
+                visibility,
+                usage: BufferUsage::STORAGE,
+                initial_data: None,
+            })
+            .expect("Failed making particle buffer")
+        };
+
+        let particle_buffer = make(
+            "[FURIKAKE] Particles",
+            capacity.particles as usize * std::mem::size_of::<Particle>(),
+            MemoryVisibility::Gpu,
+        );
+        let list_buffer = make(
+            "[FURIKAKE] Particle Lists",
+            std::mem::size_of::<ParticleListHeader>()
+                + 3 * capacity.particles as usize * std::mem::size_of::<u32>(),
+            MemoryVisibility::CpuAndGpu,
+        );
+        let emitter_buffer = make(
+            "[FURIKAKE] Particle Emitters",
+            std::mem::size_of::<EmitterHeader>()
+                + capacity.emitters as usize * std::mem::size_of::<ParticleEmitter>(),
+            MemoryVisibility::CpuAndGpu,
+        );
+
+        // Indirect buffers also need indirect usage, so take the default usage.
+        let dispatch_buffer = ctx
+            .make_buffer(&BufferInfo {
+                debug_name: "[FURIKAKE] Particle Dispatch",
+                byte_size: (2 * std::mem::size_of::<DispatchIndirectCommand>()) as u32,
+                visibility: MemoryVisibility::CpuAndGpu,
+                ..Default::default()
+            })
+            .expect("Failed making particle dispatch buffer");
+
+        Self {
+            capacity,
+            particle_buffer,
+            list_buffer,
+            emitter_buffer,
+            dispatch_buffer,
+            emitters: Vec::new(),
+            live: Vec::new(),
+            available: Vec::new(),
+            retired: Vec::new(),
+            spawn_remainder: Vec::new(),
+            primed: false,
+            current: 0,
+        }
+    }
+
+    /// Reservation for `meshi_particle_lists`.
+    pub fn list_reservation(&self) -> ReservedParticleBuffer {
+        ReservedParticleBuffer {
+            name: "meshi_particle_lists",
+            buffer: self.list_buffer,
+            layout: Some(ReservedLayout::trailing_array(
+                ParticleListHeader::shader_layout(),
+                "indices",
+                std::mem::size_of::<u32>() as u32,
+            )),
+        }
+    }
+
+    /// Reservation for `meshi_particle_emitters`.
+    pub fn emitter_reservation(&self) -> ReservedParticleBuffer {
+        ReservedParticleBuffer {
+            name: "meshi_particle_emitters",
+            buffer: self.emitter_buffer,
+            layout: Some(ReservedLayout::Elements {
+                header: std::mem::size_of::<EmitterHeader>() as u32,
+                element: ParticleEmitter::shader_layout(),
+            }),
+        }
+    }
+
+    /// Reservation for `meshi_particle_dispatch`.
+    pub fn dispatch_reservation(&self) -> ReservedParticleBuffer {
+        ReservedParticleBuffer {
+            name: "meshi_particle_dispatch",
+            buffer: self.dispatch_buffer,
+            layout: None,
+        }
+    }
+
+    pub fn capacity(&self) -> ParticleCapacity {
+        self.capacity
+    }
+
+    pub fn dispatch_buffer(&self) -> Handle<Buffer> {
+        self.dispatch_buffer
+    }
+
+    pub fn add_emitter(
+        &mut self,
+        emitter: ParticleEmitter,
+    ) -> Result<Handle<ParticleEmitter>, FurikakeError> {
+        let slot = match self.available.pop() {
+            Some(slot) => slot,
+            None if self.emitters.len() < self.capacity.emitters as usize => {
+                self.emitters.push(ParticleEmitter::default());
+                self.live.push(false);
+                self.spawn_remainder.push(0.0);
+                (self.emitters.len() - 1) as u32
+            }
+            None => {
+                return Err(FurikakeError::ReservationFull { name: self.name() });
+            }
+        };
+
+        self.emitters[slot as usize] = ParticleEmitter {
+            spawn_count: 0,
+            ..emitter
+        };
+        self.live[slot as usize] = true;
+        self.spawn_remainder[slot as usize] = 0.0;
+        slot_handle(slot).ok_or_else(|| FurikakeError::ReservationFull { name: self.name() })
+    }
+
+    /// Stops an emitter. Its record stays in place for particles still alive,
+    /// and its slot is retired until `advance` has covered its `lifetime`.
+    pub fn remove_emitter(&mut self, handle: Handle<ParticleEmitter>) {
+        let slot = handle.slot as usize;
+        if self.live.get(slot).copied().unwrap_or(false) {
+            self.live[slot] = false;
+            self.emitters[slot].spawn_rate = 0.0;
+            self.emitters[slot].spawn_count = 0;
+            self.retired
+                .push((slot as u32, self.emitters[slot].lifetime));
+        }
+    }
+
+    pub fn emitter(&self, handle: Handle<ParticleEmitter>) -> &ParticleEmitter {
+        &self.emitters[handle.slot as usize]
+    }
+
+    pub fn emitter_mut(&mut self, handle: Handle<ParticleEmitter>) -> &mut ParticleEmitter {
+        &mut self.emitters[handle.slot as usize]
+    }
+
+    /// Turns each emitter's `spawn_rate` into this frame's `spawn_count`.
+    /// Frames without an `advance` spawn nothing.
+    pub fn advance(&mut self, delta_seconds: f32) {
+        let available = &mut self.available;
+        self.retired.retain_mut(|(slot, remaining)| {
+            *remaining -= delta_seconds;
+            if *remaining > 0.0 {
+                return true;
+            }
+            available.push(*slot);
+            false
+        });
+
+        for ((emitter, remainder), live) in self
+            .emitters
+            .iter_mut()
+            .zip(&mut self.spawn_remainder)
+            .zip(&self.live)
+        {
+            if !live {
+                continue;
+            }
+            let spawn = *remainder + emitter.spawn_rate * delta_seconds;
+            emitter.spawn_count = spawn.floor() as u32;
+            *remainder = spawn.fract();
+        }
+    }
+
+    /// Alive list the simulation reads from this frame.
+    pub fn current_list(&self) -> u32 {
+        self.current
+    }
+
+    fn write_lists(&mut self, ctx: &mut Context) -> Result<(), FurikakeError> {
+        let lists = ctx
+            .map_buffer_mut::<u32>(self.list_buffer)
+            .map_err(FurikakeError::buffer_map_failed)?;
+        let header_words = std::mem::size_of::<ParticleListHeader>() / std::mem::size_of::<u32>();
+        if self.primed {
+            self.current ^= 1;
+            lists[3] = self.current;
+            lists[1 + (self.current ^ 1) as usize] = 0;
+        } else {
+            let capacity = self.capacity.particles;
+            let header = [ParticleListHeader {
+                dead_count: capacity,
+                capacity,
+                ..Default::default()
+            }];
+            lists[..header_words].copy_from_slice(unsafe { header.align_to::<u32>().1 });
+            for (index, entry) in lists[header_words..header_words + capacity as usize]
+                .iter_mut()
+                .enumerate()
+            {
+                *entry = index as u32;
+            }
+            self.primed = true;
+        }
+        ctx.unmap_buffer(self.list_buffer)
+            .map_err(FurikakeError::buffer_unmap_failed)?;
+
+        Ok(())
+    }
+
+    fn write_emitters(&mut self, ctx: &mut Context) -> Result<(), FurikakeError> {
+        let header = [EmitterHeader {
+            emitter_count: self.emitters.len() as u32,
+            ..Default::default()
+        }];
+        let header_bytes = unsafe { header.align_to::<u8>().1 };
+        let emitter_bytes = unsafe { self.emitters.align_to::<u8>().1 };
+
+        let mapped = ctx
+            .map_buffer_mut::<u8>(self.emitter_buffer)
+            .map_err(FurikakeError::buffer_map_failed)?;
+        mapped[..header_bytes.len()].copy_from_slice(header_bytes);
+        mapped[header_bytes.len()..header_bytes.len() + emitter_bytes.len()]
+            .copy_from_slice(emitter_bytes);
+        ctx.unmap_buffer(self.emitter_buffer)
+            .map_err(FurikakeError::buffer_unmap_failed)?;
+
+        Ok(())
+    }
+
+    fn write_dispatch(&mut self, ctx: &mut Context) -> Result<(), FurikakeError> {
+        let spawned: u32 = self
+            .emitters
+            .iter()
+            .map(|emitter| emitter.spawn_count)
+            .sum();
+        let groups = |count: u32| DispatchIndirectCommand {
+            x: count.div_ceil(PARTICLE_GROUP_SIZE),
+            y: 1,
+            z: 1,
+        };
+
+        // Simulation is sized for the whole pool; shaders may tighten it from
+        // `alive_count` before it is consumed.
+        let commands = ctx
+            .map_buffer_mut::<DispatchIndirectCommand>(self.dispatch_buffer)
+            .map_err(FurikakeError::buffer_map_failed)?;
+        commands[EMIT_DISPATCH] = groups(spawned.min(self.capacity.particles));
+        commands[SIMULATE_DISPATCH] = groups(self.capacity.particles);
+        ctx.unmap_buffer(self.dispatch_buffer)
+            .map_err(FurikakeError::buffer_unmap_failed)?;
+
+        Ok(())
+    }
+}
+
+impl ReservedItem for ReservedParticles {
+    fn name(&self) -> String {
+        "meshi_particles".to_string()
+    }
+
+    fn update(&mut self, ctx: &mut Context) -> Result<(), FurikakeError> {
+        self.write_lists(ctx)?;
+        self.write_emitters(ctx)?;
+        self.write_dispatch(ctx)?;
+
+        // Spawn counts are consumed by this frame only.
+        for emitter in &mut self.emitters {
+            emitter.spawn_count = 0;
+        }
+        Ok(())
+    }
+
+    fn binding(&self) -> ReservedBinding<'_> {
+        ReservedBinding::Binding(BindingInfo {
+            resource: ShaderResource::StorageBuffer(self.particle_buffer),
+            binding: 0,
+        })
+    }
+
+    fn layout(&self) -> Option<ReservedLayout> {
+        Some(ReservedLayout::Elements {
+            header: 0,
+            element: Particle::shader_layout(),
+        })
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+impl ReservedItem for ReservedParticleBuffer {
+    fn name(&self) -> String {
+        self.name.to_string()
+    }
+
+    fn update(&mut self, _ctx: &mut Context) -> Result<(), FurikakeError> {
+        // Written by `ReservedParticles::update`.
+        Ok(())
+    }
+
+    fn binding(&self) -> ReservedBinding<'_> {
+        ReservedBinding::Binding(BindingInfo {
+            resource: ShaderResource::StorageBuffer(self.buffer),
+            binding: 0,
+        })
+    }
+
+    fn layout(&self) -> Option<ReservedLayout> {
+        self.layout.clone()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resolver::spirv::SpirvModule;
+    use dashi::ContextInfo;
+    use glam::Vec3;
+
+    fn small(ctx: &mut Context) -> ReservedParticles {
+        ReservedParticles::with_capacity(
+            ctx,
+            ParticleCapacity {
+                particles: 128,
+                emitters: 2,
+            },
+        )
+    }
+
+    #[test]
+    fn initialises_lists_and_flips_alive_buffers() {
+        let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
+        let mut particles = small(&mut ctx);
+        particles.update(&mut ctx).expect("first update");
+
+        let lists = particles.list_reservation();
+        let header_words = std::mem::size_of::<ParticleListHeader>() / 4;
+        let mapped = ctx.map_buffer::<u32>(lists.buffer).expect("map lists");
+        assert_eq!(&mapped[..5], &[128, 0, 0, 0, 128]);
+        assert!(
+            mapped[header_words..header_words + 128]
+                .iter()
+                .copied()
+                .eq(0..128)
+        );
+        ctx.unmap_buffer(lists.buffer).expect("unmap lists");
+
+        // Pretend the simulation wrote 40 survivors into the output list.
+        let mapped = ctx.map_buffer_mut::<u32>(lists.buffer).expect("map lists");
+        mapped[2] = 40;
+        mapped[0] = 88;
+        ctx.unmap_buffer(lists.buffer).expect("unmap lists");
+
+        particles.update(&mut ctx).expect("second update");
+        assert_eq!(particles.current_list(), 1);
+        let mapped = ctx.map_buffer::<u32>(lists.buffer).expect("map lists");
+        assert_eq!(&mapped[..4], &[88, 0, 40, 1]);
+        ctx.unmap_buffer(lists.buffer).expect("unmap lists");
+    }
+
+    #[test]
+    fn spawns_from_emitter_rates() {
+        let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
+        let mut particles = small(&mut ctx);
+
+        let sparks = particles
+            .add_emitter(ParticleEmitter {
+                spawn_rate: 100.0,
+                direction: Vec3::Y,
+                lifetime: 2.0,
+                ..Default::default()
+            })
+            .expect("add emitter");
+        let smoke = particles
+            .add_emitter(ParticleEmitter {
+                spawn_rate: 3.0,
+                lifetime: 1.0,
+                ..Default::default()
+            })
+            .expect("add emitter");
+        assert!(matches!(
+            particles.add_emitter(ParticleEmitter::default()),
+            Err(FurikakeError::ReservationFull { .. })
+        ));
+
+        particles.advance(0.5);
+        assert_eq!(particles.emitter(sparks).spawn_count, 50);
+        assert_eq!(particles.emitter(smoke).spawn_count, 1);
+        particles.update(&mut ctx).expect("update");
+
+        let commands = ctx
+            .map_buffer::<DispatchIndirectCommand>(particles.dispatch_buffer())
+            .expect("map dispatch");
+        assert_eq!(commands[EMIT_DISPATCH].x, 1);
+        assert_eq!(commands[SIMULATE_DISPATCH].x, 2);
+        ctx.unmap_buffer(particles.dispatch_buffer())
+            .expect("unmap dispatch");
+        assert_eq!(particles.emitter(sparks).spawn_count, 0);
+
+        particles.remove_emitter(smoke);
+        assert_eq!(particles.emitter(smoke).spawn_rate, 0.0);
+
+        // Smoke particles may live another second, so the slot stays retired.
+        particles.advance(0.5);
+        assert!(matches!(
+            particles.add_emitter(ParticleEmitter::default()),
+            Err(FurikakeError::ReservationFull { .. })
+        ));
+
+        particles.advance(0.5);
+        let reused = particles
+            .add_emitter(ParticleEmitter::default())
+            .expect("reuse emitter slot");
+        assert_eq!(reused.slot, smoke.slot);
+    }
+
+    #[test]
+    fn headers_match_documented_blocks() {
+        let spirv: &[u32] = inline_spirv::inline_spirv!(
+            r#"
+            #version 450 core
+            layout(local_size_x = 1) in;
+
+            struct ParticleEmitter {
+                vec3 position;
+                float spawn_rate;
+                vec3 direction;
+                float spread;
+                vec4 color;
+                float speed;
+                float lifetime;
+                float size;
+                uint spawn_count;
+            };
+            layout(set = 0, binding = 0) buffer ParticleLists {
+                uint dead_count;
+                uint alive_count[2];
+                uint current;
+                uint capacity;
+                uint indices[];
+            } meshi_particle_lists;
+            layout(set = 0, binding = 1) buffer ParticleEmitters {
+                uint emitter_count;
+                ParticleEmitter emitters[];
+            } meshi_particle_emitters;
+
+            void main() {
+                uint current = meshi_particle_lists.current;
+                meshi_particle_lists.indices[current] =
+                    meshi_particle_emitters.emitters[current].spawn_count +
+                    meshi_particle_emitters.emitter_count;
+            }
+            "#,
+            comp
+        );
+        let module = SpirvModule::parse(spirv).expect("parse spirv");
+
+        let lists = module
+            .variable_block("meshi_particle_lists")
+            .expect("lists block");
+        let indices = lists.block.member("indices").expect("indices member");
+        assert_eq!(
+            indices.offset as usize,
+            std::mem::size_of::<ParticleListHeader>()
+        );
+
+        let emitters = module
+            .variable_block("meshi_particle_emitters")
+            .expect("emitters block");
+        let records = emitters.block.member("emitters").expect("emitters member");
+        assert_eq!(
+            records.offset as usize,
+            std::mem::size_of::<EmitterHeader>()
+        );
+        assert_eq!(
+            records.array_stride,
+            Some(std::mem::size_of::<ParticleEmitter>() as u32)
+        );
+    }
+}
@@ -0,0 +1,249 @@
+use dashi::Context;
+
+use crate::{
+    error::FurikakeError,
+    resolver::{LayoutMismatch, LayoutMismatchKind, StructLayout},
+};
+
+use super::{ReservedBinding, ReservedItem};
+
+/// Plain data that can be copied into a push-constant block byte for byte.
+///
+/// # Safety
+///
+/// Implementors must have no padding bytes and no invalid bit patterns, so
+/// every byte of a value is initialized.
+pub unsafe trait PushConstantValue: Copy + 'static {}
+
+macro_rules! push_constant_values {
+    ($($ty:ty),* $(,)?) => {
+        $(unsafe impl PushConstantValue for $ty {})*
+    };
+}
+
+push_constant_values!(
+    u32,
+    i32,
+    f32,
+    u64,
+    i64,
+    f64,
+    glam::Vec2,
+    glam::Vec3,
+    glam::Vec4,
+    glam::UVec2,
+    glam::UVec3,
+    glam::UVec4,
+    glam::IVec2,
+    glam::IVec3,
+    glam::IVec4,
+    glam::Mat4,
+);
+
+unsafe impl<T: PushConstantValue, const N: usize> PushConstantValue for [T; N] {}
+
+/// Named per-draw values destined for a push-constant block.
+#[derive(Clone, Debug, Default)]
+pub struct PushConstants {
+    values: Vec<(String, Vec<u8>)>,
+}
+
+impl PushConstants {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets `member` to the bytes of `value`.
+    pub fn set<T: PushConstantValue>(&mut self, member: &str, value: T) {
+        // SAFETY: `PushConstantValue` types have no uninitialized bytes.
+        let bytes = unsafe {
+            std::slice::from_raw_parts((&value as *const T).cast::<u8>(), std::mem::size_of::<T>())
+        }
+        .to_vec();
+
+        match self.values.iter_mut().find(|(name, _)| name == member) {
+            Some((_, existing)) => *existing = bytes,
+            None => self.values.push((member.to_string(), bytes)),
+        }
+    }
+
+    pub fn bytes(&self, member: &str) -> Option<&[u8]> {
+        self.values
+            .iter()
+            .find(|(name, _)| name == member)
+            .map(|(_, bytes)| bytes.as_slice())
+    }
+
+    /// Packs the values into a buffer laid out like `layout`. Members the
+    /// shader does not declare are skipped; members without a value are zero.
+    pub fn pack(&self, layout: &StructLayout) -> Result<Vec<u8>, FurikakeError> {
+        let mut packed = vec![0; layout.size as usize];
+        for (name, bytes) in &self.values {
+            let Some(member) = layout.member(name) else {
+                continue;
+            };
+            if bytes.len() != member.size as usize {
+                return Err(FurikakeError::PushConstantMismatch {
+                    mismatch: LayoutMismatch {
+                        reservation: layout.name.clone(),
+                        member: name.clone(),
+                        kind: LayoutMismatchKind::Size {
+                            rust: bytes.len() as u32,
+                            shader: member.size,
+                        },
+                    },
+                });
+            }
+
+            let offset = member.offset as usize;
+            packed[offset..offset + bytes.len()].copy_from_slice(bytes);
+        }
+        Ok(packed)
+    }
+}
+
+/// A push-constant block reserved by name. Values are set per draw and
+/// packed against the block layout the resolver reflects from the shader.
+pub struct ReservedPushConstants {
+    name: String,
+    values: PushConstants,
+}
+
+impl ReservedPushConstants {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            values: PushConstants::new(),
+        }
+    }
+
+    /// The `meshi_draw` block:
+    ///
+    /// ```glsl
+    /// layout(push_constant) uniform Draw {
+    ///     uint object_index;
+    ///     uint material_index;
+    /// } meshi_draw;
+    /// ```
+    pub fn draw() -> Self {
+        let mut draw = Self::new("meshi_draw");
+        draw.set_draw(0, 0);
+        draw
+    }
+
+    pub fn values(&self) -> &PushConstants {
+        &self.values
+    }
+
+    pub fn values_mut(&mut self) -> &mut PushConstants {
+        &mut self.values
+    }
+
+    pub fn set_draw(&mut self, object_index: u32, material_index: u32) {
+        self.values.set("object_index", object_index);
+        self.values.set("material_index", material_index);
+    }
+
+    pub fn pack(&self, layout: &StructLayout) -> Result<Vec<u8>, FurikakeError> {
+        self.values.pack(layout)
+    }
+}
+
+impl ReservedItem for ReservedPushConstants {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn update(&mut self, _ctx: &mut Context) -> Result<(), FurikakeError> {
+        // Push constants are recorded per draw, nothing lives on the GPU.
+        Ok(())
+    }
+
+    fn binding(&self) -> ReservedBinding<'_> {
+        ReservedBinding::PushConstant(&self.values)
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resolver::MemberLayout;
+
+    fn draw_layout() -> StructLayout {
+        let member = |name: &str, offset, size| MemberLayout {
+            name: name.to_string(),
+            offset,
+            size,
+            array_stride: None,
+        };
+        StructLayout {
+            name: "Draw".to_string(),
+            size: 16,
+            members: vec![
+                member("object_index", 0, 4),
+                member("material_index", 4, 4),
+                member("tint", 8, 8),
+            ],
+        }
+    }
+
+    #[test]
+    fn packs_values_at_member_offsets() {
+        let mut draw = ReservedPushConstants::draw();
+        draw.set_draw(3, 7);
+        draw.values_mut().set("tint", [0.5f32, 1.0]);
+        draw.values_mut().set("unused", 9u32);
+
+        let packed = draw.pack(&draw_layout()).expect("pack push constants");
+        assert_eq!(packed.len(), 16);
+        assert_eq!(&packed[0..4], &3u32.to_ne_bytes());
+        assert_eq!(&packed[4..8], &7u32.to_ne_bytes());
+        assert_eq!(&packed[8..12], &0.5f32.to_ne_bytes());
+        assert_eq!(&packed[12..16], &1.0f32.to_ne_bytes());
+    }
+
+    fn size_mismatch(values: &PushConstants) -> Option<(String, LayoutMismatchKind)> {
+        match values.pack(&draw_layout()) {
+            Err(FurikakeError::PushConstantMismatch { mismatch }) => {
+                Some((mismatch.member, mismatch.kind))
+            }
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn rejects_oversized_values() {
+        let mut values = PushConstants::new();
+        values.set("object_index", 1u64);
+
+        assert_eq!(
+            size_mismatch(&values),
+            Some((
+                "object_index".to_string(),
+                LayoutMismatchKind::Size { rust: 8, shader: 4 }
+            ))
+        );
+    }
+
+    #[test]
+    fn rejects_undersized_values() {
+        let mut values = PushConstants::new();
+        values.set("tint", 0.5f32);
+
+        assert_eq!(
+            size_mismatch(&values),
+            Some((
+                "tint".to_string(),
+                LayoutMismatchKind::Size { rust: 4, shader: 8 }
+            ))
+        );
+    }
+}
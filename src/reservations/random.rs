@@ -0,0 +1,323 @@
+use dashi::{
+    BindingInfo, Buffer, BufferInfo, BufferView, Context, Handle, MemoryVisibility, ShaderResource,
+};
+use glam::Vec2;
+
+use crate::{
+    error::FurikakeError,
+    resolver::{ReservedLayout, ShaderLayout},
+    types::Texture,
+};
+
+use super::{ReservedBinding, ReservedItem};
+
+/// Per-frame random state laid out for a std140 uniform:
+///
+/// ```glsl
+/// layout(set = N, binding = 0) uniform Random {
+///     uint frame_index;
+///     uint seed;
+///     uint blue_noise_texture_id;
+///     uint blue_noise_size;
+///     vec2 blue_noise_offset;
+/// } meshi_random;
+/// ```
+///
+/// `blue_noise_offset` steps through the R2 sequence each frame so sampling
+/// the tile at `pixel + offset * size` decorrelates consecutive frames.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RandomData {
+    pub frame_index: u32,
+    pub seed: u32,
+    pub blue_noise_texture_id: u32,
+    pub blue_noise_size: u32,
+    pub blue_noise_offset: Vec2,
+    pub _padding: Vec2,
+}
+
+crate::shader_layout!(RandomData {
+    frame_index,
+    seed,
+    blue_noise_texture_id,
+    blue_noise_size,
+    blue_noise_offset,
+    _padding
+});
+
+/// Tileable blue-noise tile. `ranks` holds the void-and-cluster ordering,
+/// `values` the same ordering quantised to an R8 UNORM image.
+pub struct BlueNoise {
+    pub size: u32,
+    pub ranks: Vec<u32>,
+    pub values: Vec<u8>,
+}
+
+/// PCG-based integer hash, matching the common GLSL `pcg_hash`.
+pub fn pcg_hash(value: u32) -> u32 {
+    let state = value.wrapping_mul(747_796_405).wrapping_add(2_891_336_453);
+    let word = ((state >> ((state >> 28) + 4)) ^ state).wrapping_mul(277_803_737);
+    (word >> 22) ^ word
+}
+
+/// Generates a `size` x `size` tileable blue-noise tile with the
+/// void-and-cluster method, using a toroidal Gaussian energy filter.
+pub fn generate_blue_noise(size: u32, seed: u32) -> BlueNoise {
+    const SIGMA: f32 = 1.5;
+
+    assert!(size > 0, "blue noise tile must not be empty");
+    let side = size as usize;
+    let count = side * side;
+
+    let mut weights = vec![0.0; count];
+    for dy in 0..side {
+        for dx in 0..side {
+            let x = dx.min(side - dx) as f32;
+            let y = dy.min(side - dy) as f32;
+            weights[dy * side + dx] = (-(x * x + y * y) / (2.0 * SIGMA * SIGMA)).exp();
+        }
+    }
+
+    let splat = |energy: &mut [f32], pixel: usize, sign: f32| {
+        let (px, py) = (pixel % side, pixel / side);
+        for qy in 0..side {
+            let row = ((qy + side - py) % side) * side;
+            for qx in 0..side {
+                energy[qy * side + qx] += sign * weights[row + (qx + side - px) % side];
+            }
+        }
+    };
+    let tightest_cluster = |set: &[bool], energy: &[f32]| {
+        (0..count)
+            .filter(|p| set[*p])
+            .max_by(|a, b| energy[*a].total_cmp(&energy[*b]))
+            .expect("pattern has no set pixels")
+    };
+    let largest_void = |set: &[bool], energy: &[f32]| {
+        (0..count)
+            .filter(|p| !set[*p])
+            .min_by(|a, b| energy[*a].total_cmp(&energy[*b]))
+            .expect("pattern has no empty pixels")
+    };
+
+    // Seed roughly a tenth of the pixels at random.
+    let initial = (count / 10).max(1);
+    let mut set = vec![false; count];
+    let mut energy = vec![0.0; count];
+    let mut placed = 0;
+    let mut attempt = 0;
+    while placed < initial {
+        let pixel = pcg_hash(seed ^ pcg_hash(attempt)) as usize % count;
+        attempt += 1;
+        if !set[pixel] {
+            set[pixel] = true;
+            splat(&mut energy, pixel, 1.0);
+            placed += 1;
+        }
+    }
+
+    let mut ranks = vec![0; count];
+    if initial < count {
+        // Spread the initial pattern until moving the tightest cluster into
+        // the largest void puts it back where it came from. Float ties can
+        // make two pixels swap back and forth, so the swaps are capped.
+        for _ in 0..count {
+            let cluster = tightest_cluster(&set, &energy);
+            set[cluster] = false;
+            splat(&mut energy, cluster, -1.0);
+            let void = largest_void(&set, &energy);
+            set[void] = true;
+            splat(&mut energy, void, 1.0);
+            if void == cluster {
+                break;
+            }
+        }
+
+        // Rank the initial pattern by removing clusters.
+        let mut removed = set.clone();
+        let mut removed_energy = energy.clone();
+        for rank in (0..initial).rev() {
+            let cluster = tightest_cluster(&removed, &removed_energy);
+            removed[cluster] = false;
+            splat(&mut removed_energy, cluster, -1.0);
+            ranks[cluster] = rank as u32;
+        }
+
+        // Rank the rest by filling voids.
+        for rank in initial..count {
+            let void = largest_void(&set, &energy);
+            set[void] = true;
+            splat(&mut energy, void, 1.0);
+            ranks[void] = rank as u32;
+        }
+    }
+
+    let values = ranks
+        .iter()
+        .map(|rank| ((*rank as u64 * 256) / count as u64) as u8)
+        .collect();
+
+    BlueNoise {
+        size,
+        ranks,
+        values,
+    }
+}
+
+/// Frame-varying seed and blue-noise tile for stochastic effects. The frame
+/// index follows `meshi_timing`; `BindlessState::update` keeps them in step.
+pub struct ReservedRandom {
+    data: RandomData,
+    base_seed: u32,
+    blue_noise: Option<BlueNoise>,
+    buffer: Handle<Buffer>,
+}
+
+impl ReservedRandom {
+    pub fn new(ctx: &mut Context) -> Self {
+        let buffer = ctx
+            .make_buffer(&BufferInfo {
+                debug_name: "[FURIKAKE] Random Buffer",
+                byte_size: std::mem::size_of::<RandomData>() as u32,
+                visibility: MemoryVisibility::CpuAndGpu,
+                ..Default::default()
+            })
+            .expect("Unable to make random buffer!");
+
+        let mut random = Self {
+            data: RandomData {
+                blue_noise_texture_id: u32::MAX,
+                ..Default::default()
+            },
+            base_seed: 0,
+            blue_noise: None,
+            buffer,
+        };
+        random.set_frame_index(0);
+        random
+    }
+
+    pub fn buffer(&self) -> Handle<Buffer> {
+        self.buffer
+    }
+
+    pub fn data(&self) -> &RandomData {
+        &self.data
+    }
+
+    pub fn set_base_seed(&mut self, seed: u32) {
+        self.base_seed = seed;
+        self.set_frame_index(self.data.frame_index);
+    }
+
+    /// Derives this frame's seed and noise offset from `frame_index`.
+    pub fn set_frame_index(&mut self, frame_index: u32) {
+        // Plastic-number R2 sequence.
+        const R2: Vec2 = Vec2::new(0.754_877_7, 0.569_840_3);
+
+        self.data.frame_index = frame_index;
+        self.data.seed = pcg_hash(frame_index ^ pcg_hash(self.base_seed));
+        self.data.blue_noise_offset = (Vec2::splat(0.5) + R2 * frame_index as f32).fract();
+    }
+
+    /// Stores a generated tile and the bindless texture slot it is bound to.
+    pub fn set_blue_noise(&mut self, noise: BlueNoise, texture: Handle<Texture>) {
+        self.data.blue_noise_texture_id = texture.slot as u32;
+        self.data.blue_noise_size = noise.size;
+        self.blue_noise = Some(noise);
+    }
+
+    /// The tile the application should upload for `blue_noise_texture_id`.
+    pub fn blue_noise(&self) -> Option<&BlueNoise> {
+        self.blue_noise.as_ref()
+    }
+}
+
+impl ReservedItem for ReservedRandom {
+    fn name(&self) -> String {
+        "meshi_random".to_string()
+    }
+
+    fn update(&mut self, ctx: &mut Context) -> Result<(), FurikakeError> {
+        let s = ctx
+            .map_buffer_mut::<RandomData>(self.buffer)
+            .map_err(FurikakeError::buffer_map_failed)?;
+        s[0] = self.data;
+        ctx.unmap_buffer(self.buffer)
+            .map_err(FurikakeError::buffer_unmap_failed)?;
+
+        Ok(())
+    }
+
+    fn binding(&self) -> ReservedBinding<'_> {
+        ReservedBinding::Binding(BindingInfo {
+            resource: ShaderResource::ConstBuffer(BufferView {
+                handle: self.buffer,
+                size: std::mem::size_of::<RandomData>() as u64,
+                offset: 0,
+            }),
+            binding: 0,
+        })
+    }
+
+    fn layout(&self) -> Option<ReservedLayout> {
+        Some(ReservedLayout::Block(RandomData::shader_layout()))
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blue_noise_ranks_are_a_spread_permutation() {
+        let size = 16;
+        let noise = generate_blue_noise(size, 7);
+        let count = (size * size) as usize;
+
+        let mut sorted = noise.ranks.clone();
+        sorted.sort_unstable();
+        assert!(sorted.iter().copied().eq(0..count as u32));
+        assert_eq!(noise.values.len(), count);
+
+        // The darkest eighth of the tile forms a dither pattern with no two
+        // points touching, wrapping across the tile edges.
+        let side = size as i32;
+        let points: Vec<(i32, i32)> = (0..count)
+            .filter(|p| (noise.ranks[*p] as usize) < count / 8)
+            .map(|p| ((p as i32) % side, (p as i32) / side))
+            .collect();
+        for (i, a) in points.iter().enumerate() {
+            for b in &points[i + 1..] {
+                let dx = (a.0 - b.0).abs().min(side - (a.0 - b.0).abs());
+                let dy = (a.1 - b.1).abs().min(side - (a.1 - b.1).abs());
+                assert!(dx > 1 || dy > 1, "{a:?} touches {b:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn seeds_follow_the_frame_index() {
+        let mut ctx = Context::headless(&dashi::ContextInfo::default()).expect("create context");
+        let mut random = ReservedRandom::new(&mut ctx);
+
+        random.set_frame_index(3);
+        let seed = random.data().seed;
+        random.set_frame_index(4);
+        assert_ne!(random.data().seed, seed);
+        random.set_frame_index(3);
+        assert_eq!(random.data().seed, seed);
+
+        random.set_base_seed(99);
+        assert_ne!(random.data().seed, seed);
+        assert_eq!(random.data().frame_index, 3);
+    }
+}
@@ -3,15 +3,25 @@ use dashi::{
 };
 use std::time::Instant;
 
+use crate::resolver::{ReservedLayout, ShaderLayout};
+
 use super::{ReservedBinding, ReservedItem};
 #[repr(C)]
 struct TimeData {
     current_time_ms: f32,
     frame_time_ms: f32,
+    frame_index: u32,
 }
 
+crate::shader_layout!(TimeData {
+    current_time_ms,
+    frame_time_ms,
+    frame_index
+});
+
 pub struct ReservedTiming {
     last_time: Instant,
+    frame_index: u32,
     buffer: Handle<Buffer>,
 }
 
@@ -28,6 +38,7 @@ impl ReservedTiming {
 
         Self {
             last_time: Instant::now(),
+            frame_index: 0,
             buffer,
         }
     }
@@ -36,6 +47,11 @@ impl ReservedTiming {
         self.buffer
     }
 
+    /// Index of the frame the next `update()` uploads.
+    pub fn frame_index(&self) -> u32 {
+        self.frame_index
+    }
+
     pub fn set_last_time(&mut self, instant: Instant) {
         self.last_time = instant;
     }
@@ -54,6 +70,8 @@ impl ReservedItem for ReservedTiming {
         s[0].current_time_ms = now.elapsed().as_secs_f32() * 1000.0;
         s[0].frame_time_ms = (now - self.last_time).as_secs_f32() * 1000.0;
         self.last_time = now;
+        s[0].frame_index = self.frame_index;
+        self.frame_index = self.frame_index.wrapping_add(1);
         ctx.unmap_buffer(self.buffer)
             .map_err(crate::error::FurikakeError::buffer_unmap_failed)?;
 
@@ -64,13 +82,17 @@ impl ReservedItem for ReservedTiming {
         return ReservedBinding::Binding(BindingInfo {
             resource: ShaderResource::ConstBuffer(BufferView {
                 handle: self.buffer,
-                size: (std::mem::size_of::<f32>() * 2) as u64,
+                size: std::mem::size_of::<TimeData>() as u64,
                 offset: 0,
             }),
             binding: 0,
         });
     }
 
+    fn layout(&self) -> Option<ReservedLayout> {
+        Some(ReservedLayout::Block(TimeData::shader_layout()))
+    }
+
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
@@ -0,0 +1,262 @@
+use dashi::{
+    BindingInfo, Buffer, BufferInfo, BufferView, Context, Handle, MemoryVisibility, ShaderResource,
+};
+use glam::{UVec2, Vec2};
+
+use crate::{
+    error::FurikakeError,
+    resolver::{ReservedLayout, ShaderLayout},
+    types::Viewport,
+};
+
+use super::{ReservedBinding, ReservedItem};
+
+/// Maximum number of viewports addressable from shaders.
+pub const MAX_VIEWPORTS: usize = 8;
+
+/// Header written in front of the viewport array, padded to a std140 vec4.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct ViewportHeader {
+    viewport_count: u32,
+    _padding: [u32; 3],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct ViewportData {
+    header: ViewportHeader,
+    viewports: [Viewport; MAX_VIEWPORTS],
+}
+
+/// Application-side description of one viewport.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ViewportDesc {
+    pub size: UVec2,
+    pub offset: UVec2,
+    /// Dynamic-resolution scale applied to `size` to get the render resolution.
+    pub render_scale: f32,
+}
+
+impl ViewportDesc {
+    pub fn new(size: UVec2) -> Self {
+        Self {
+            size,
+            offset: UVec2::ZERO,
+            render_scale: 1.0,
+        }
+    }
+
+    fn record(&self) -> Viewport {
+        let output_resolution = self.size.as_vec2();
+        let resolution = (output_resolution * self.render_scale)
+            .round()
+            .max(Vec2::ONE);
+        Viewport {
+            resolution,
+            inv_resolution: resolution.recip(),
+            output_resolution,
+            offset: self.offset.as_vec2(),
+            render_scale: self.render_scale,
+            _padding: [0.0; 3],
+        }
+    }
+}
+
+/// Named viewports exposed as one uniform block:
+///
+/// ```glsl
+/// layout(set = N, binding = 0) uniform Viewports {
+///     uint viewport_count;
+///     Viewport viewports[8];
+/// } meshi_viewport;
+/// ```
+///
+/// The `"main"` viewport always exists at index 0. Split-screen views are
+/// added by name and select their entry by index, e.g. through a push constant.
+pub struct ReservedViewport {
+    buffer: Handle<Buffer>,
+    names: Vec<String>,
+    viewports: Vec<ViewportDesc>,
+}
+
+impl ReservedViewport {
+    pub const MAIN: &'static str = "main";
+
+    pub fn new(ctx: &mut Context) -> Self {
+        let buffer = ctx
+            .make_buffer(&BufferInfo {
+                debug_name: "[FURIKAKE] Viewport Buffer",
+                byte_size: std::mem::size_of::<ViewportData>() as u32,
+                visibility: MemoryVisibility::CpuAndGpu,
+                ..Default::default()
+            })
+            .expect("Unable to make viewport buffer!");
+
+        Self {
+            buffer,
+            names: vec![Self::MAIN.to_string()],
+            viewports: vec![ViewportDesc::new(UVec2::ONE)],
+        }
+    }
+
+    pub fn buffer(&self) -> Handle<Buffer> {
+        self.buffer
+    }
+
+    pub fn viewport_count(&self) -> u32 {
+        self.viewports.len() as u32
+    }
+
+    /// Resizes the main viewport, typically from the window resize event.
+    pub fn resize(&mut self, size: UVec2) {
+        self.viewports[0].size = size;
+    }
+
+    /// Adds a named viewport and returns its index in `viewports[]`.
+    pub fn add_viewport(&mut self, name: &str, desc: ViewportDesc) -> Result<u32, FurikakeError> {
+        if let Some(index) = self.viewport_index(name) {
+            self.viewports[index as usize] = desc;
+            return Ok(index);
+        }
+
+        if self.viewports.len() == MAX_VIEWPORTS {
+            return Err(FurikakeError::ReservationFull { name: self.name() });
+        }
+
+        self.names.push(name.to_string());
+        self.viewports.push(desc);
+        Ok((self.viewports.len() - 1) as u32)
+    }
+
+    /// Removes a named viewport. Viewports after it move down one index.
+    /// The main viewport cannot be removed.
+    pub fn remove_viewport(&mut self, name: &str) -> Option<ViewportDesc> {
+        let index = self.viewport_index(name).filter(|index| *index != 0)? as usize;
+        self.names.remove(index);
+        Some(self.viewports.remove(index))
+    }
+
+    pub fn viewport_index(&self, name: &str) -> Option<u32> {
+        self.names
+            .iter()
+            .position(|existing| existing == name)
+            .map(|index| index as u32)
+    }
+
+    pub fn viewport(&self, name: &str) -> Option<&ViewportDesc> {
+        let index = self.viewport_index(name)?;
+        self.viewports.get(index as usize)
+    }
+
+    pub fn viewport_mut(&mut self, name: &str) -> Option<&mut ViewportDesc> {
+        let index = self.viewport_index(name)?;
+        self.viewports.get_mut(index as usize)
+    }
+}
+
+impl ReservedItem for ReservedViewport {
+    fn name(&self) -> String {
+        "meshi_viewport".to_string()
+    }
+
+    fn update(&mut self, ctx: &mut Context) -> Result<(), FurikakeError> {
+        let s = ctx
+            .map_buffer_mut::<ViewportData>(self.buffer)
+            .map_err(FurikakeError::buffer_map_failed)?;
+        s[0].header.viewport_count = self.viewport_count();
+        for (dst, desc) in s[0].viewports.iter_mut().zip(&self.viewports) {
+            *dst = desc.record();
+        }
+        ctx.unmap_buffer(self.buffer)
+            .map_err(FurikakeError::buffer_unmap_failed)?;
+
+        Ok(())
+    }
+
+    fn binding(&self) -> ReservedBinding<'_> {
+        ReservedBinding::Binding(BindingInfo {
+            resource: ShaderResource::ConstBuffer(BufferView {
+                handle: self.buffer,
+                size: std::mem::size_of::<ViewportData>() as u64,
+                offset: 0,
+            }),
+            binding: 0,
+        })
+    }
+
+    fn layout(&self) -> Option<ReservedLayout> {
+        Some(ReservedLayout::Elements {
+            header: std::mem::size_of::<ViewportHeader>() as u32,
+            element: Viewport::shader_layout(),
+        })
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dashi::ContextInfo;
+
+    #[test]
+    fn writes_named_viewports() {
+        let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
+        let mut viewport = ReservedViewport::new(&mut ctx);
+
+        viewport.resize(UVec2::new(1920, 1080));
+        let right = viewport
+            .add_viewport(
+                "player_two",
+                ViewportDesc {
+                    size: UVec2::new(960, 1080),
+                    offset: UVec2::new(960, 0),
+                    render_scale: 0.5,
+                },
+            )
+            .expect("add viewport");
+        assert_eq!(right, 1);
+        viewport.update(&mut ctx).expect("update viewport");
+
+        let data = ctx
+            .map_buffer::<ViewportData>(viewport.buffer())
+            .expect("map viewport buffer")[0];
+        assert_eq!(data.header.viewport_count, 2);
+        assert_eq!(data.viewports[0].resolution, Vec2::new(1920.0, 1080.0));
+        assert_eq!(data.viewports[1].resolution, Vec2::new(480.0, 540.0));
+        assert_eq!(
+            data.viewports[1].inv_resolution,
+            Vec2::new(1.0 / 480.0, 1.0 / 540.0)
+        );
+        assert_eq!(data.viewports[1].offset, Vec2::new(960.0, 0.0));
+        ctx.unmap_buffer(viewport.buffer())
+            .expect("unmap viewport buffer");
+
+        assert!(viewport.remove_viewport(ReservedViewport::MAIN).is_none());
+        assert!(viewport.remove_viewport("player_two").is_some());
+        assert_eq!(viewport.viewport_count(), 1);
+    }
+
+    #[test]
+    fn rejects_too_many_viewports() {
+        let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
+        let mut viewport = ReservedViewport::new(&mut ctx);
+
+        for index in 1..MAX_VIEWPORTS {
+            viewport
+                .add_viewport(&format!("view_{index}"), ViewportDesc::new(UVec2::ONE))
+                .expect("add viewport");
+        }
+        assert!(matches!(
+            viewport.add_viewport("overflow", ViewportDesc::new(UVec2::ONE)),
+            Err(FurikakeError::ReservationFull { .. })
+        ));
+    }
+}
@@ -0,0 +1,207 @@
+/// Offset and size of one member of a reflected block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MemberLayout {
+    pub name: String,
+    pub offset: u32,
+    /// Size in bytes; zero for runtime arrays.
+    pub size: u32,
+    /// Element stride for array members.
+    pub array_stride: Option<u32>,
+}
+
+/// Memory layout of a struct or block as the shader declares it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StructLayout {
+    pub name: String,
+    /// End of the last sized member, not rounded up to the struct alignment.
+    pub size: u32,
+    pub members: Vec<MemberLayout>,
+}
+
+impl StructLayout {
+    pub fn member(&self, name: &str) -> Option<&MemberLayout> {
+        self.members.iter().find(|member| member.name == name)
+    }
+}
+
+/// Describes the memory layout of a Rust type written into a reserved binding.
+/// Implement with [`shader_layout!`](crate::shader_layout).
+pub trait ShaderLayout {
+    fn shader_layout() -> StructLayout;
+}
+
+/// The Rust-side layout a reservation writes, compared against the shader by
+/// the [`Resolver`](super::Resolver).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReservedLayout {
+    /// The whole block, as for uniforms.
+    Block(StructLayout),
+    /// Elements of the block's trailing array member, as for record buffers.
+    /// `header` is the byte size of what the Rust side writes in front of the
+    /// array, where the shader's trailing member must start.
+    Elements { header: u32, element: StructLayout },
+}
+
+impl ReservedLayout {
+    /// `header`'s members followed by a runtime array of scalars or vectors
+    /// `stride` bytes apart, such as `uint indices[]`.
+    pub fn trailing_array(mut header: StructLayout, member: &str, stride: u32) -> Self {
+        header.members.push(MemberLayout {
+            name: member.to_string(),
+            offset: header.size,
+            size: 0,
+            array_stride: Some(stride),
+        });
+        Self::Block(header)
+    }
+}
+
+/// Size of the field `field` selects. Used by [`shader_layout!`](crate::shader_layout).
+pub fn field_size<T, F>(_field: fn(&T) -> &F) -> u32 {
+    std::mem::size_of::<F>() as u32
+}
+
+/// Implements [`ShaderLayout`] for a `#[repr(C)]` struct from its field list.
+/// Array fields name their element type so the array stride is recorded:
+///
+/// ```ignore
+/// shader_layout!(Environment { irradiance_sh[Vec4], sun_direction, exposure });
+/// ```
+#[macro_export]
+macro_rules! shader_layout {
+    (@stride) => {
+        None
+    };
+    (@stride $elem:ty) => {
+        Some(std::mem::size_of::<$elem>() as u32)
+    };
+    ($ty:ident { $($field:ident $([$elem:ty])?),* $(,)? }) => {
+        impl $crate::resolver::ShaderLayout for $ty {
+            fn shader_layout() -> $crate::resolver::StructLayout {
+                $crate::resolver::StructLayout {
+                    name: stringify!($ty).to_string(),
+                    size: std::mem::size_of::<$ty>() as u32,
+                    members: vec![$(
+                        $crate::resolver::MemberLayout {
+                            name: stringify!($field).to_string(),
+                            offset: std::mem::offset_of!($ty, $field) as u32,
+                            size: $crate::resolver::layout::field_size(|v: &$ty| &v.$field),
+                            array_stride: $crate::shader_layout!(@stride $($elem)?),
+                        }
+                    ),*],
+                }
+            }
+        }
+    };
+}
+
+/// How a reflected member differs from the Rust type.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LayoutMismatchKind {
+    Offset {
+        rust: u32,
+        shader: u32,
+    },
+    Size {
+        rust: u32,
+        shader: u32,
+    },
+    ArrayStride {
+        rust: u32,
+        shader: u32,
+    },
+    /// The shader declares a member past the end of the Rust type.
+    MissingInRust,
+}
+
+/// One difference between a reservation's Rust layout and the shader's.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LayoutMismatch {
+    pub reservation: String,
+    /// Shader member name; Rust field name in parentheses when they differ.
+    pub member: String,
+    pub kind: LayoutMismatchKind,
+}
+
+impl std::fmt::Display for LayoutMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "`{}` member `{}`: ", self.reservation, self.member)?;
+        match self.kind {
+            LayoutMismatchKind::Offset { rust, shader } => {
+                write!(f, "Rust offset {} but shader offset {}", rust, shader)
+            }
+            LayoutMismatchKind::Size { rust, shader } => {
+                write!(f, "Rust size {} but shader size {}", rust, shader)
+            }
+            LayoutMismatchKind::ArrayStride { rust, shader } => {
+                write!(f, "Rust array stride {} but shader stride {}", rust, shader)
+            }
+            LayoutMismatchKind::MissingInRust => write!(f, "not present in the Rust type"),
+        }
+    }
+}
+
+fn is_padding(member: &MemberLayout) -> bool {
+    member.name.starts_with('_')
+}
+
+/// Compares the non-padding members of `rust` and `shader` in declaration
+/// order, so members may be renamed and padding may be implicit on either
+/// side. The shader may declare a prefix of the Rust members.
+pub fn compare_layouts(
+    reservation: &str,
+    rust: &StructLayout,
+    shader: &StructLayout,
+) -> Vec<LayoutMismatch> {
+    let mut rust_members = rust.members.iter().filter(|m| !is_padding(m));
+    let mut mismatches = Vec::new();
+    for shader_member in shader.members.iter().filter(|m| !is_padding(m)) {
+        let mismatch = |member: String, kind| LayoutMismatch {
+            reservation: reservation.to_string(),
+            member,
+            kind,
+        };
+        let Some(rust_member) = rust_members.next() else {
+            mismatches.push(mismatch(
+                shader_member.name.clone(),
+                LayoutMismatchKind::MissingInRust,
+            ));
+            continue;
+        };
+
+        let member = if rust_member.name == shader_member.name {
+            shader_member.name.clone()
+        } else {
+            format!("{} ({})", shader_member.name, rust_member.name)
+        };
+        if rust_member.offset != shader_member.offset {
+            mismatches.push(mismatch(
+                member.clone(),
+                LayoutMismatchKind::Offset {
+                    rust: rust_member.offset,
+                    shader: shader_member.offset,
+                },
+            ));
+        }
+        if rust_member.size != shader_member.size {
+            mismatches.push(mismatch(
+                member.clone(),
+                LayoutMismatchKind::Size {
+                    rust: rust_member.size,
+                    shader: shader_member.size,
+                },
+            ));
+        }
+        if let Some((rust, shader)) = rust_member
+            .array_stride
+            .zip(shader_member.array_stride)
+            .filter(|(rust, shader)| rust != shader)
+        {
+            mismatches.push(mismatch(
+                member,
+                LayoutMismatchKind::ArrayStride { rust, shader },
+            ));
+        }
+    }
+    mismatches
+}
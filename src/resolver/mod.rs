@@ -1,4 +1,17 @@
+pub mod layout;
+pub(crate) mod spirv;
+
+pub use layout::{
+    LayoutMismatch, LayoutMismatchKind, MemberLayout, ReservedLayout, ShaderLayout, StructLayout,
+    compare_layouts,
+};
+
 use crate::{GPUState, ReservedMetadata};
+use spirv::{ReflectedBlock, SpirvModule};
+
+/// Prefix shared by every reserved name. Shader variables using it are
+/// expected to match a reservation.
+pub const RESERVED_PREFIX: &str = "meshi_";
 
 #[cfg(test)]
 use dashi::BindGroupVariableType;
@@ -11,19 +24,85 @@ pub struct ResolveResult {
     pub set: Option<u32>,
 }
 
+/// A reserved push-constant block and the layout the shader declares for it.
+#[derive(Default, Debug)]
+pub struct PushConstantResult {
+    pub name: String,
+    pub exists: bool,
+    pub layout: Option<StructLayout>,
+}
+
+/// Something suspicious in a shader that does not stop it from resolving.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ResolverWarning {
+    /// A variable uses [`RESERVED_PREFIX`] but matches no reservation.
+    UnknownReservedName {
+        name: String,
+        /// Closest reserved name by edit distance, when one is close enough.
+        suggestion: Option<String>,
+    },
+}
+
+impl std::fmt::Display for ResolverWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResolverWarning::UnknownReservedName { name, suggestion } => {
+                write!(f, "`{}` is not a reserved name", name)?;
+                if let Some(suggestion) = suggestion {
+                    write!(f, "; did you mean `{}`?", suggestion)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Resolver {
     resolved: Vec<ResolveResult>,
+    push_constants: Vec<PushConstantResult>,
+    warnings: Vec<ResolverWarning>,
 }
 impl Resolver {
     pub fn new<T: GPUState>(
-        _state: &T,
+        state: &T,
         result: &bento::CompilationResult,
     ) -> Result<Self, crate::error::FurikakeError> {
-        let names = T::reserved_metadata();
+        let names = state.metadata();
+        // Bento does not report block layouts or push constants, so those
+        // are read from the SPIR-V directly.
+        let module = if result.spirv.is_empty() {
+            None
+        } else {
+            Some(SpirvModule::parse(&result.spirv)?)
+        };
+
+        let resolved = Self::reflect_bindings(&names, result)?;
+        if let Some(module) = &module {
+            Self::validate_layouts(state, &resolved, module)?;
+        }
+
+        let mut shader_names: Vec<String> = result
+            .variables
+            .iter()
+            .map(|var| var.name.clone())
+            .collect();
+        if let Some(module) = &module {
+            shader_names.extend(
+                module
+                    .push_constant_blocks()
+                    .into_iter()
+                    .map(|(name, _)| name),
+            );
+        }
 
         Ok(Self {
-            resolved: Self::reflect_bindings(names, result)?,
+            resolved,
+            push_constants: Self::reflect_push_constants(
+                T::reserved_push_constants(),
+                module.as_ref(),
+            ),
+            warnings: Self::find_unknown_names::<T>(&names, &shader_names),
         })
     }
 
@@ -31,6 +110,156 @@ impl Resolver {
         self.resolved.as_slice()
     }
 
+    pub fn push_constants(&self) -> &[PushConstantResult] {
+        self.push_constants.as_slice()
+    }
+
+    pub fn push_constant(&self, name: &str) -> Option<&PushConstantResult> {
+        self.push_constants.iter().find(|pc| pc.name == name)
+    }
+
+    pub fn warnings(&self) -> &[ResolverWarning] {
+        self.warnings.as_slice()
+    }
+
+    /// Closest reserved name suggested for the unknown shader name `name`.
+    pub fn suggestion(&self, name: &str) -> Option<&str> {
+        self.warnings.iter().find_map(|warning| match warning {
+            ResolverWarning::UnknownReservedName {
+                name: unknown,
+                suggestion,
+            } if unknown == name => suggestion.as_deref(),
+            _ => None,
+        })
+    }
+
+    fn find_unknown_names<T: GPUState>(
+        names: &[ReservedMetadata],
+        shader_names: &[String],
+    ) -> Vec<ResolverWarning> {
+        let reserved = || {
+            names
+                .iter()
+                .map(|meta| meta.name)
+                .chain(T::reserved_push_constants().iter().copied())
+        };
+
+        let mut warnings = Vec::new();
+        for (i, name) in shader_names.iter().enumerate() {
+            let known = reserved().any(|reserved| reserved == name.as_str());
+            if known || !name.starts_with(RESERVED_PREFIX) || shader_names[..i].contains(name) {
+                continue;
+            }
+
+            // Only suggest names a typo away: at most a third of the name differs.
+            let suggestion = reserved()
+                .map(|reserved| (edit_distance(name, reserved), reserved))
+                .min()
+                .filter(|(distance, _)| *distance <= name.len() / 3)
+                .map(|(_, reserved)| reserved.to_string());
+            warnings.push(ResolverWarning::UnknownReservedName {
+                name: name.clone(),
+                suggestion,
+            });
+        }
+        warnings
+    }
+
+    fn reflect_push_constants(
+        names: &[&str],
+        module: Option<&SpirvModule>,
+    ) -> Vec<PushConstantResult> {
+        let blocks = module
+            .map(SpirvModule::push_constant_blocks)
+            .unwrap_or_default();
+
+        names
+            .iter()
+            .map(|name| {
+                let layout = blocks
+                    .iter()
+                    .find(|(block, layout)| block == name || layout.name == *name)
+                    .map(|(_, layout)| layout.clone());
+                PushConstantResult {
+                    name: name.to_string(),
+                    exists: layout.is_some(),
+                    layout,
+                }
+            })
+            .collect()
+    }
+
+    /// Compares the Rust layout of every reservation the shader binds with
+    /// the block it declares, collecting every mismatch into one error.
+    fn validate_layouts<T: GPUState>(
+        state: &T,
+        resolved: &[ResolveResult],
+        module: &SpirvModule,
+    ) -> Result<(), crate::error::FurikakeError> {
+        let mut mismatches = Vec::new();
+        for found in resolved.iter().filter(|r| r.exists) {
+            let Some(layout) = state
+                .binding(&found.name)
+                .ok()
+                .and_then(|item| item.layout())
+            else {
+                continue;
+            };
+            let Some(block) = module.variable_block(&found.name) else {
+                continue;
+            };
+            mismatches.extend(Self::compare_block(&found.name, &layout, &block));
+        }
+
+        if mismatches.is_empty() {
+            Ok(())
+        } else {
+            Err(crate::error::FurikakeError::LayoutMismatch { mismatches })
+        }
+    }
+
+    fn compare_block(
+        name: &str,
+        layout: &ReservedLayout,
+        block: &ReflectedBlock,
+    ) -> Vec<LayoutMismatch> {
+        let (header, rust, elements) = match layout {
+            ReservedLayout::Block(rust) => return compare_layouts(name, rust, &block.block),
+            ReservedLayout::Elements { header, element } => (*header, element, &block.elements),
+        };
+
+        // Without a trailing array the shader declares one record per
+        // descriptor, as bindless pools do.
+        let Some((element, stride)) = elements else {
+            return compare_layouts(name, rust, &block.block);
+        };
+
+        let mut mismatches = compare_layouts(name, rust, element);
+        let Some(array) = block.block.members.last() else {
+            return mismatches;
+        };
+        let mut mismatch = |kind| {
+            mismatches.push(LayoutMismatch {
+                reservation: name.to_string(),
+                member: array.name.clone(),
+                kind,
+            })
+        };
+        if array.offset != header {
+            mismatch(LayoutMismatchKind::Offset {
+                rust: header,
+                shader: array.offset,
+            });
+        }
+        if let Some(stride) = stride.filter(|stride| *stride != rust.size) {
+            mismatch(LayoutMismatchKind::ArrayStride {
+                rust: rust.size,
+                shader: stride,
+            });
+        }
+        mismatches
+    }
+
     fn reflect_bindings(
         names: &[ReservedMetadata],
         res: &bento::CompilationResult,
@@ -66,6 +295,22 @@ impl Resolver {
     }
 }
 
+/// Levenshtein distance between `a` and `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, b) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(a != *b);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -78,7 +323,7 @@ mod tests {
             &["meshi_timing"]
         }
 
-        fn reserved_metadata() -> &'static [ReservedMetadata] {
+        fn reserved_metadata() -> &'static [ReservedMetadata<'static>] {
             &[ReservedMetadata {
                 name: "meshi_timing",
                 kind: BindGroupVariableType::Uniform,
@@ -93,6 +338,134 @@ mod tests {
                 name: key.to_string(),
             })
         }
+
+        fn reserved_push_constants() -> &'static [&'static str] {
+            &["meshi_draw"]
+        }
+    }
+
+    /// `TestState` plus reservations registered at runtime.
+    struct RegisteredState {
+        registered: Vec<String>,
+    }
+
+    impl GPUState for RegisteredState {
+        fn reserved_names() -> &'static [&'static str] {
+            TestState::reserved_names()
+        }
+
+        fn reserved_metadata() -> &'static [ReservedMetadata<'static>] {
+            TestState::reserved_metadata()
+        }
+
+        fn binding(
+            &self,
+            key: &str,
+        ) -> Result<&dyn crate::reservations::ReservedItem, FurikakeError> {
+            TestState.binding(key)
+        }
+
+        fn metadata(&self) -> Vec<ReservedMetadata<'_>> {
+            let registered = self.registered.iter().map(|name| ReservedMetadata {
+                name,
+                kind: BindGroupVariableType::Storage,
+            });
+            Self::reserved_metadata()
+                .iter()
+                .cloned()
+                .chain(registered)
+                .collect()
+        }
+    }
+
+    /// Binds a Rust layout under `meshi_bindless_cameras` without any GPU data.
+    struct CameraState;
+
+    impl crate::reservations::ReservedItem for CameraState {
+        fn name(&self) -> String {
+            "meshi_bindless_cameras".to_string()
+        }
+
+        fn update(&mut self, _ctx: &mut dashi::Context) -> Result<(), FurikakeError> {
+            Ok(())
+        }
+
+        fn binding(&self) -> crate::reservations::ReservedBinding<'_> {
+            crate::reservations::ReservedBinding::BindlessBinding(dashi::IndexedBindingInfo {
+                resources: &[],
+                binding: 0,
+            })
+        }
+
+        fn layout(&self) -> Option<ReservedLayout> {
+            Some(ReservedLayout::Elements {
+                header: 0,
+                element: crate::types::Camera::shader_layout(),
+            })
+        }
+
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+            self
+        }
+    }
+
+    impl GPUState for CameraState {
+        fn reserved_names() -> &'static [&'static str] {
+            &["meshi_bindless_cameras"]
+        }
+
+        fn reserved_metadata() -> &'static [ReservedMetadata<'static>] {
+            &[ReservedMetadata {
+                name: "meshi_bindless_cameras",
+                kind: BindGroupVariableType::Storage,
+            }]
+        }
+
+        fn binding(
+            &self,
+            _key: &str,
+        ) -> Result<&dyn crate::reservations::ReservedItem, FurikakeError> {
+            Ok(self)
+        }
+    }
+
+    fn camera_result(spirv: &[u32]) -> bento::CompilationResult {
+        let mut res = make_result(vec![bento::ShaderVariable {
+            name: "meshi_bindless_cameras".to_string(),
+            set: 0,
+            kind: dashi::BindGroupVariable {
+                var_type: BindGroupVariableType::Storage,
+                binding: 0,
+                count: 4,
+            },
+        }]);
+        res.spirv = spirv.to_vec();
+        res
+    }
+
+    /// Storage buffers named `names` at consecutive bindings of set 0.
+    fn storage_result(spirv: &[u32], names: &[&str]) -> bento::CompilationResult {
+        let mut res = make_result(
+            names
+                .iter()
+                .enumerate()
+                .map(|(binding, name)| bento::ShaderVariable {
+                    name: name.to_string(),
+                    set: 0,
+                    kind: dashi::BindGroupVariable {
+                        var_type: BindGroupVariableType::Storage,
+                        binding: binding as u32,
+                        count: 1,
+                    },
+                })
+                .collect(),
+        );
+        res.spirv = spirv.to_vec();
+        res
     }
 
     fn make_result(variables: Vec<bento::ShaderVariable>) -> bento::CompilationResult {
@@ -146,4 +519,363 @@ mod tests {
             other => panic!("unexpected error {other:?}", other = other),
         }
     }
+
+    #[test]
+    fn reflects_push_constant_layout() {
+        let spirv: &[u32] = inline_spirv::inline_spirv!(
+            r#"
+            #version 450 core
+            layout(push_constant) uniform Draw {
+                uint object_index;
+                uint material_index;
+                mat4 model;
+            } meshi_draw;
+
+            void main() {
+                gl_Position = meshi_draw.model
+                    * vec4(float(meshi_draw.object_index + meshi_draw.material_index));
+            }
+            "#,
+            vert
+        );
+        let mut res = make_result(vec![]);
+        res.spirv = spirv.to_vec();
+
+        let result = Resolver::new(&TestState, &res).expect("resolver result");
+        let draw = result.push_constant("meshi_draw").expect("draw block");
+        assert!(draw.exists);
+
+        let layout = draw.layout.as_ref().expect("draw layout");
+        assert_eq!(layout.name, "Draw");
+        assert_eq!(layout.size, 80);
+        let model = layout.member("model").expect("model member");
+        assert_eq!((model.offset, model.size), (16, 64));
+        assert_eq!(layout.member("material_index").map(|m| m.offset), Some(4));
+    }
+
+    #[test]
+    fn rejects_invalid_spirv_for_push_constants() {
+        let mut res = make_result(vec![]);
+        res.spirv = vec![0xdead_beef; 8];
+
+        assert!(matches!(
+            Resolver::new(&TestState, &res),
+            Err(FurikakeError::ResolverReflection { .. })
+        ));
+    }
+
+    #[test]
+    fn accepts_matching_struct_layout() {
+        let spirv: &[u32] = inline_spirv::inline_spirv!(
+            r#"
+            #version 450 core
+            layout(local_size_x = 1) in;
+
+            layout(set = 0, binding = 0) buffer Camera {
+                vec3 position;
+                float _padding0;
+                vec4 rotation;
+            } meshi_bindless_cameras[4];
+
+            void main() {
+                meshi_bindless_cameras[0].rotation = vec4(meshi_bindless_cameras[1].position, 1.0);
+            }
+            "#,
+            comp
+        );
+
+        let result = Resolver::new(&CameraState, &camera_result(spirv)).expect("resolver result");
+        assert!(result.resolved()[0].exists);
+    }
+
+    #[test]
+    fn reports_struct_layout_mismatch() {
+        let spirv: &[u32] = inline_spirv::inline_spirv!(
+            r#"
+            #version 450 core
+            layout(local_size_x = 1) in;
+
+            layout(set = 0, binding = 0) buffer Camera {
+                vec3 position;
+                vec3 rotation;
+            } meshi_bindless_cameras[4];
+
+            void main() {
+                meshi_bindless_cameras[0].rotation = meshi_bindless_cameras[1].position;
+            }
+            "#,
+            comp
+        );
+
+        let err = Resolver::new(&CameraState, &camera_result(spirv)).unwrap_err();
+        let mismatches = match err {
+            FurikakeError::LayoutMismatch { mismatches } => mismatches,
+            other => panic!("unexpected error {other:?}", other = other),
+        };
+        assert_eq!(
+            mismatches,
+            vec![LayoutMismatch {
+                reservation: "meshi_bindless_cameras".to_string(),
+                member: "rotation".to_string(),
+                kind: LayoutMismatchKind::Size {
+                    rust: 16,
+                    shader: 12,
+                },
+            }]
+        );
+    }
+
+    #[test]
+    fn accepts_printf_and_particle_blocks() {
+        let spirv: &[u32] = inline_spirv::inline_spirv!(
+            r#"
+            #version 450 core
+            layout(local_size_x = 1) in;
+
+            struct PrintfRecord {
+                uint format_id;
+                uint value_count;
+                uint values[6];
+            };
+            struct Particle {
+                vec3 position;
+                float age;
+                vec3 velocity;
+                float lifetime;
+                vec4 color;
+                float size;
+                uint emitter_index;
+                uint _padding[2];
+            };
+            layout(set = 0, binding = 0) buffer DebugPrintf {
+                uint head;
+                uint capacity;
+                PrintfRecord records[];
+            } meshi_debug_printf;
+            layout(set = 0, binding = 1) buffer Particles {
+                Particle particles[];
+            } meshi_particles;
+
+            void main() {
+                uint i = atomicAdd(meshi_debug_printf.head, 1) % meshi_debug_printf.capacity;
+                meshi_debug_printf.records[i].format_id = meshi_particles.particles[i].emitter_index;
+            }
+            "#,
+            comp
+        );
+
+        let mut ctx =
+            dashi::Context::headless(&dashi::ContextInfo::default()).expect("create context");
+        let state = crate::BindlessState::new(&mut ctx);
+        let res = storage_result(spirv, &["meshi_debug_printf", "meshi_particles"]);
+        let result = Resolver::new(&state, &res).expect("resolver result");
+        assert!(
+            result
+                .resolved()
+                .iter()
+                .filter(|r| r.name == "meshi_debug_printf" || r.name == "meshi_particles")
+                .all(|r| r.exists)
+        );
+    }
+
+    #[test]
+    fn reports_misplaced_trailing_arrays() {
+        let spirv: &[u32] = inline_spirv::inline_spirv!(
+            r#"
+            #version 450 core
+            layout(local_size_x = 1) in;
+
+            struct PrintfRecord {
+                uint format_id;
+                uint value_count;
+                uint values[6];
+            };
+            struct Particle {
+                vec3 position;
+                float age;
+                vec3 velocity;
+                float lifetime;
+                vec4 color;
+                float size;
+                uint emitter_index;
+                uint _padding[2];
+            };
+            layout(set = 0, binding = 0) buffer DebugPrintf {
+                uint head;
+                uint capacity;
+                uint dropped;
+                PrintfRecord records[];
+            } meshi_debug_printf;
+            layout(set = 0, binding = 1) buffer Particles {
+                uint particle_count;
+                Particle particles[];
+            } meshi_particles;
+
+            void main() {
+                uint i = atomicAdd(meshi_debug_printf.head, 1) % meshi_debug_printf.capacity;
+                meshi_debug_printf.records[i].format_id = meshi_particles.particles[i].emitter_index;
+            }
+            "#,
+            comp
+        );
+
+        let mut ctx =
+            dashi::Context::headless(&dashi::ContextInfo::default()).expect("create context");
+        let state = crate::BindlessState::new(&mut ctx);
+        let res = storage_result(spirv, &["meshi_debug_printf", "meshi_particles"]);
+        let mismatches = match Resolver::new(&state, &res) {
+            Err(FurikakeError::LayoutMismatch { mismatches }) => mismatches,
+            other => panic!("unexpected result {other:?}"),
+        };
+        assert_eq!(
+            mismatches,
+            vec![
+                LayoutMismatch {
+                    reservation: "meshi_debug_printf".to_string(),
+                    member: "records".to_string(),
+                    kind: LayoutMismatchKind::Offset {
+                        rust: 8,
+                        shader: 12,
+                    },
+                },
+                LayoutMismatch {
+                    reservation: "meshi_particles".to_string(),
+                    member: "particles".to_string(),
+                    kind: LayoutMismatchKind::Offset {
+                        rust: 0,
+                        shader: 16,
+                    },
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn validates_anonymous_blocks_by_block_name() {
+        let spirv: &[u32] = inline_spirv::inline_spirv!(
+            r#"
+            #version 450 core
+            layout(local_size_x = 1) in;
+
+            layout(set = 0, binding = 0) buffer meshi_bindless_indices {
+                uint indices[];
+            };
+            layout(set = 0, binding = 1) buffer meshi_light_clusters {
+                uvec4 dims;
+                vec4 depth;
+                uvec4 clusters[];
+            };
+
+            void main() {
+                clusters[0].x = indices[0] + dims.x;
+            }
+            "#,
+            comp
+        );
+
+        let mut ctx =
+            dashi::Context::headless(&dashi::ContextInfo::default()).expect("create context");
+        let state = crate::BindlessState::new(&mut ctx);
+        let res = storage_result(spirv, &["meshi_bindless_indices", "meshi_light_clusters"]);
+        let mismatches = match Resolver::new(&state, &res) {
+            Err(FurikakeError::LayoutMismatch { mismatches }) => mismatches,
+            other => panic!("unexpected result {other:?}"),
+        };
+        assert_eq!(
+            mismatches,
+            vec![LayoutMismatch {
+                reservation: "meshi_light_clusters".to_string(),
+                member: "clusters".to_string(),
+                kind: LayoutMismatchKind::ArrayStride {
+                    rust: 8,
+                    shader: 16,
+                },
+            }]
+        );
+    }
+
+    #[test]
+    fn tolerates_truncated_instructions() {
+        let words = [
+            0x0723_0203,
+            0x0001_0000,
+            0,
+            8,
+            0,
+            // OpName without a name.
+            (2 << 16) | 5,
+            1,
+            // OpMemberName without a member index.
+            (2 << 16) | 6,
+            1,
+            // OpTypeStruct without a result id.
+            (1 << 16) | 30,
+        ];
+        assert!(SpirvModule::parse(&words).is_ok());
+    }
+
+    #[test]
+    fn warns_about_unknown_reserved_names() {
+        let variable = |name: &str| bento::ShaderVariable {
+            name: name.to_string(),
+            set: 0,
+            kind: dashi::BindGroupVariable {
+                var_type: BindGroupVariableType::Uniform,
+                binding: 0,
+                count: 1,
+            },
+        };
+        let res = make_result(vec![
+            variable("meshi_timimg"),
+            variable("meshi_timimg"),
+            variable("meshi_fog_volume"),
+            variable("gbuffer_albedo"),
+        ]);
+
+        let result = Resolver::new(&TestState, &res).expect("resolver result");
+        assert_eq!(
+            result.warnings(),
+            [
+                ResolverWarning::UnknownReservedName {
+                    name: "meshi_timimg".to_string(),
+                    suggestion: Some("meshi_timing".to_string()),
+                },
+                ResolverWarning::UnknownReservedName {
+                    name: "meshi_fog_volume".to_string(),
+                    suggestion: None,
+                },
+            ]
+        );
+        assert_eq!(result.suggestion("meshi_timimg"), Some("meshi_timing"));
+        assert_eq!(
+            edit_distance("meshi_bindless_texture", "meshi_bindless_textures"),
+            1
+        );
+    }
+
+    #[test]
+    fn resolves_names_registered_at_runtime() {
+        let state = RegisteredState {
+            registered: vec!["meshi_terrain_materials".to_string()],
+        };
+        let res = make_result(vec![bento::ShaderVariable {
+            name: "meshi_terrain_materials".to_string(),
+            set: 1,
+            kind: dashi::BindGroupVariable {
+                var_type: BindGroupVariableType::Storage,
+                binding: 2,
+                count: 1,
+            },
+        }]);
+
+        let result = Resolver::new(&state, &res).expect("resolver result");
+        let terrain = result
+            .resolved()
+            .iter()
+            .find(|r| r.name == "meshi_terrain_materials")
+            .expect("registered name resolved");
+        assert!(terrain.exists);
+        assert_eq!(terrain.set, Some(1));
+        assert!(result.warnings().is_empty());
+    }
 }
@@ -0,0 +1,302 @@
+//! Just enough SPIR-V parsing to recover block layouts that bento's
+//! reflection does not report.
+
+use std::collections::HashMap;
+
+use crate::error::FurikakeError;
+
+use super::layout::{MemberLayout, StructLayout};
+
+const MAGIC: u32 = 0x0723_0203;
+const HEADER_WORDS: usize = 5;
+
+const OP_NAME: u32 = 5;
+const OP_MEMBER_NAME: u32 = 6;
+const OP_TYPE_INT: u32 = 21;
+const OP_TYPE_FLOAT: u32 = 22;
+const OP_TYPE_VECTOR: u32 = 23;
+const OP_TYPE_MATRIX: u32 = 24;
+const OP_TYPE_ARRAY: u32 = 28;
+const OP_TYPE_RUNTIME_ARRAY: u32 = 29;
+const OP_TYPE_STRUCT: u32 = 30;
+const OP_TYPE_POINTER: u32 = 32;
+const OP_CONSTANT: u32 = 43;
+const OP_VARIABLE: u32 = 59;
+const OP_DECORATE: u32 = 71;
+const OP_MEMBER_DECORATE: u32 = 72;
+
+const DECORATION_ROW_MAJOR: u32 = 4;
+const DECORATION_ARRAY_STRIDE: u32 = 6;
+const DECORATION_MATRIX_STRIDE: u32 = 7;
+const DECORATION_OFFSET: u32 = 35;
+
+const STORAGE_CLASS_PUSH_CONSTANT: u32 = 9;
+
+#[derive(Clone, Copy, Debug)]
+enum SpirvType {
+    Scalar { width: u32 },
+    Vector { component: u32, count: u32 },
+    Matrix { column: u32, count: u32 },
+    Array { element: u32, length: u32 },
+    RuntimeArray { element: u32 },
+    Struct,
+    Pointer { storage_class: u32, pointee: u32 },
+}
+
+#[derive(Default)]
+struct MemberDecorations {
+    offset: Option<u32>,
+    matrix_stride: Option<u32>,
+    row_major: bool,
+}
+
+/// A reflected buffer or uniform block.
+pub(crate) struct ReflectedBlock {
+    pub block: StructLayout,
+    /// Element struct and array stride of the trailing array member, if any.
+    pub elements: Option<(StructLayout, Option<u32>)>,
+}
+
+#[derive(Default)]
+pub(crate) struct SpirvModule {
+    names: HashMap<u32, String>,
+    member_names: HashMap<(u32, u32), String>,
+    member_decorations: HashMap<(u32, u32), MemberDecorations>,
+    array_strides: HashMap<u32, u32>,
+    types: HashMap<u32, SpirvType>,
+    struct_members: HashMap<u32, Vec<u32>>,
+    constants: HashMap<u32, u32>,
+    // (variable id, pointer type id)
+    variables: Vec<(u32, u32)>,
+}
+
+fn literal_string(words: &[u32]) -> String {
+    let bytes: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+    let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+impl SpirvModule {
+    pub(crate) fn parse(words: &[u32]) -> Result<Self, FurikakeError> {
+        if words.len() < HEADER_WORDS || words[0] != MAGIC {
+            return Err(FurikakeError::ResolverReflection {
+                source: "shader is not a SPIR-V module".to_string(),
+            });
+        }
+
+        let mut module = Self::default();
+        let mut cursor = HEADER_WORDS;
+        while cursor < words.len() {
+            let count = (words[cursor] >> 16) as usize;
+            let opcode = words[cursor] & 0xffff;
+            if count == 0 || cursor + count > words.len() {
+                return Err(FurikakeError::ResolverReflection {
+                    source: format!("malformed SPIR-V instruction at word {cursor}"),
+                });
+            }
+            module.record(opcode, &words[cursor + 1..cursor + count]);
+            cursor += count;
+        }
+        Ok(module)
+    }
+
+    fn record(&mut self, opcode: u32, ops: &[u32]) {
+        let at = |index: usize| ops.get(index).copied().unwrap_or_default();
+        match opcode {
+            OP_NAME => {
+                let name = literal_string(ops.get(1..).unwrap_or_default());
+                self.names.insert(at(0), name);
+            }
+            OP_MEMBER_NAME => {
+                let name = literal_string(ops.get(2..).unwrap_or_default());
+                self.member_names.insert((at(0), at(1)), name);
+            }
+            OP_TYPE_INT | OP_TYPE_FLOAT => {
+                self.types
+                    .insert(at(0), SpirvType::Scalar { width: at(1) / 8 });
+            }
+            OP_TYPE_VECTOR => {
+                self.types.insert(
+                    at(0),
+                    SpirvType::Vector {
+                        component: at(1),
+                        count: at(2),
+                    },
+                );
+            }
+            OP_TYPE_MATRIX => {
+                self.types.insert(
+                    at(0),
+                    SpirvType::Matrix {
+                        column: at(1),
+                        count: at(2),
+                    },
+                );
+            }
+            OP_TYPE_ARRAY => {
+                let length = self.constants.get(&at(2)).copied().unwrap_or_default();
+                self.types.insert(
+                    at(0),
+                    SpirvType::Array {
+                        element: at(1),
+                        length,
+                    },
+                );
+            }
+            OP_TYPE_RUNTIME_ARRAY => {
+                self.types
+                    .insert(at(0), SpirvType::RuntimeArray { element: at(1) });
+            }
+            OP_TYPE_STRUCT => {
+                self.types.insert(at(0), SpirvType::Struct);
+                let members = ops.get(1..).unwrap_or_default().to_vec();
+                self.struct_members.insert(at(0), members);
+            }
+            OP_TYPE_POINTER => {
+                self.types.insert(
+                    at(0),
+                    SpirvType::Pointer {
+                        storage_class: at(1),
+                        pointee: at(2),
+                    },
+                );
+            }
+            OP_CONSTANT => {
+                self.constants.insert(at(1), at(2));
+            }
+            OP_VARIABLE => self.variables.push((at(1), at(0))),
+            OP_DECORATE if at(1) == DECORATION_ARRAY_STRIDE => {
+                self.array_strides.insert(at(0), at(2));
+            }
+            OP_MEMBER_DECORATE => {
+                let decorations = self.member_decorations.entry((at(0), at(1))).or_default();
+                match at(2) {
+                    DECORATION_OFFSET => decorations.offset = Some(at(3)),
+                    DECORATION_MATRIX_STRIDE => decorations.matrix_stride = Some(at(3)),
+                    DECORATION_ROW_MAJOR => decorations.row_major = true,
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn type_size(&self, id: u32, member: Option<&MemberDecorations>) -> u32 {
+        match self.types.get(&id) {
+            Some(SpirvType::Scalar { width }) => *width,
+            Some(SpirvType::Vector { component, count }) => {
+                self.type_size(*component, None) * count
+            }
+            Some(SpirvType::Matrix { column, count }) => {
+                let rows = match self.types.get(column) {
+                    Some(SpirvType::Vector { count, .. }) => *count,
+                    _ => 1,
+                };
+                let row_major = member.is_some_and(|m| m.row_major);
+                let stride = member.and_then(|m| m.matrix_stride).unwrap_or(16);
+                if row_major {
+                    rows * stride
+                } else {
+                    count * stride
+                }
+            }
+            Some(SpirvType::Array { length, .. }) => {
+                length * self.array_strides.get(&id).copied().unwrap_or_default()
+            }
+            Some(SpirvType::Struct) => self.layout_of(id).map(|l| l.size).unwrap_or_default(),
+            _ => 0,
+        }
+    }
+
+    /// Layout of the struct type `id`, or `None` if `id` is not a struct.
+    pub(crate) fn layout_of(&self, id: u32) -> Option<StructLayout> {
+        let members = self.struct_members.get(&id)?;
+        let members: Vec<MemberLayout> = members
+            .iter()
+            .enumerate()
+            .map(|(index, ty)| {
+                let key = (id, index as u32);
+                let decorations = self.member_decorations.get(&key);
+                MemberLayout {
+                    name: self.member_names.get(&key).cloned().unwrap_or_default(),
+                    offset: decorations.and_then(|d| d.offset).unwrap_or_default(),
+                    size: self.type_size(*ty, decorations),
+                    array_stride: self.array_strides.get(ty).copied(),
+                }
+            })
+            .collect();
+
+        Some(StructLayout {
+            name: self.names.get(&id).cloned().unwrap_or_default(),
+            size: members
+                .iter()
+                .map(|member| member.offset + member.size)
+                .max()
+                .unwrap_or_default(),
+            members,
+        })
+    }
+
+    fn array_element(&self, id: u32) -> Option<u32> {
+        match self.types.get(&id) {
+            Some(SpirvType::Array { element, .. } | SpirvType::RuntimeArray { element }) => {
+                Some(*element)
+            }
+            _ => None,
+        }
+    }
+
+    /// Instance name of `variable`, or the name of its block type `block` for
+    /// blocks declared without one.
+    fn instance_name(&self, variable: u32, block: u32) -> Option<&str> {
+        self.names
+            .get(&variable)
+            .filter(|name| !name.is_empty())
+            .or_else(|| self.names.get(&block))
+            .map(String::as_str)
+    }
+
+    /// The block behind the variable named `name`, looking through descriptor
+    /// arrays, plus the element struct and stride of its trailing array member.
+    /// Anonymous blocks are found by their block name.
+    pub(crate) fn variable_block(&self, name: &str) -> Option<ReflectedBlock> {
+        let block_id = self.variables.iter().find_map(|(variable, pointer)| {
+            let Some(SpirvType::Pointer { pointee, .. }) = self.types.get(pointer) else {
+                return None;
+            };
+            let block_id = self.array_element(*pointee).unwrap_or(*pointee);
+            (self.instance_name(*variable, block_id) == Some(name)).then_some(block_id)
+        })?;
+        let block = self.layout_of(block_id)?;
+
+        let elements = self
+            .struct_members
+            .get(&block_id)
+            .and_then(|members| members.last())
+            .and_then(|last| {
+                let element = self.layout_of(self.array_element(*last)?)?;
+                Some((element, self.array_strides.get(last).copied()))
+            });
+
+        Some(ReflectedBlock { block, elements })
+    }
+
+    /// Push-constant blocks as `(name, layout)`. The name is the instance
+    /// name, or the block name for blocks declared without one.
+    pub(crate) fn push_constant_blocks(&self) -> Vec<(String, StructLayout)> {
+        self.variables
+            .iter()
+            .filter_map(|(variable, pointer)| match self.types.get(pointer) {
+                Some(SpirvType::Pointer {
+                    storage_class: STORAGE_CLASS_PUSH_CONSTANT,
+                    pointee,
+                }) => {
+                    let layout = self.layout_of(*pointee)?;
+                    let name = self.instance_name(*variable, *pointee).unwrap_or_default();
+                    Some((name.to_string(), layout))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+}
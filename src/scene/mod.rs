@@ -0,0 +1,443 @@
+use dashi::Handle;
+use glam::{Mat4, Quat, Vec3};
+
+use crate::{
+    error::FurikakeError, reservations::bindless_transformations::ReservedBindlessTransformations,
+    types::Transformation,
+};
+
+/// Translation, rotation and scale of a node relative to its parent.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LocalTransform {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl Default for LocalTransform {
+    fn default() -> Self {
+        Self {
+            translation: Vec3::ZERO,
+            rotation: Quat::IDENTITY,
+            scale: Vec3::ONE,
+        }
+    }
+}
+
+impl LocalTransform {
+    pub fn from_translation(translation: Vec3) -> Self {
+        Self {
+            translation,
+            ..Default::default()
+        }
+    }
+
+    pub fn as_matrix(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+}
+
+/// A node in a [`SceneGraph`]. Each node owns one slot in the bindless
+/// transformation pool that receives its world matrix.
+pub struct SceneNode {
+    local: LocalTransform,
+    world: Mat4,
+    parent: Option<Handle<SceneNode>>,
+    children: Vec<Handle<SceneNode>>,
+    transformation: Handle<Transformation>,
+    dirty: bool,
+    alive: bool,
+    /// Bumped on removal so handles to the old node stop matching.
+    generation: u16,
+}
+
+/// Parent/child hierarchy whose world matrices are written into
+/// `meshi_bindless_transformations`.
+#[derive(Default)]
+pub struct SceneGraph {
+    nodes: Vec<SceneNode>,
+    available: Vec<u32>,
+    /// Nodes marked dirty since the last update; each roots a subtree to recompute.
+    dirty: Vec<Handle<SceneNode>>,
+}
+
+impl SceneGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a node under `parent` (or as a root) and allocates its transformation slot.
+    /// Fails when `parent` was removed or never belonged to this graph.
+    pub fn add_node(
+        &mut self,
+        transformations: &mut ReservedBindlessTransformations,
+        parent: Option<Handle<SceneNode>>,
+    ) -> Result<Handle<SceneNode>, FurikakeError> {
+        if parent.is_some_and(|parent| !self.contains(parent)) {
+            return Err(stale_handle());
+        }
+
+        let (next, generation) = match self.available.last() {
+            Some(index) => (*index, self.nodes[*index as usize].generation),
+            None => (self.nodes.len() as u32, 0),
+        };
+        let handle = u16::try_from(next)
+            .map(|slot| Handle::new(slot, generation))
+            .map_err(|_| FurikakeError::ReservationFull {
+                name: "scene graph".to_string(),
+            })?;
+
+        let node = SceneNode {
+            local: LocalTransform::default(),
+            world: Mat4::IDENTITY,
+            parent,
+            children: Vec::new(),
+            transformation: transformations.add_transformation()?,
+            dirty: true,
+            alive: true,
+            generation,
+        };
+
+        if let Some(index) = self.available.pop() {
+            self.nodes[index as usize] = node;
+        } else {
+            self.nodes.push(node);
+        }
+
+        if let Some(parent) = parent {
+            self.nodes[parent.slot as usize].children.push(handle);
+        }
+        self.dirty.push(handle);
+        Ok(handle)
+    }
+
+    /// Removes `node` and its whole subtree, releasing their transformation slots.
+    pub fn remove_node(
+        &mut self,
+        transformations: &mut ReservedBindlessTransformations,
+        node: Handle<SceneNode>,
+    ) {
+        if !self.contains(node) {
+            return;
+        }
+
+        self.detach(node);
+
+        let mut stack = vec![node];
+        while let Some(current) = stack.pop() {
+            let entry = &mut self.nodes[current.slot as usize];
+            entry.alive = false;
+            entry.generation = entry.generation.wrapping_add(1);
+            stack.append(&mut entry.children);
+            transformations.remove_transformation(entry.transformation);
+            self.available.push(u32::from(current.slot));
+        }
+    }
+
+    /// Moves `node` under `parent`, or makes it a root when `parent` is `None`.
+    /// Fails on stale handles and when `parent` lies in `node`'s subtree.
+    pub fn set_parent(
+        &mut self,
+        node: Handle<SceneNode>,
+        parent: Option<Handle<SceneNode>>,
+    ) -> Result<(), FurikakeError> {
+        self.entry(node)?;
+        if let Some(parent) = parent {
+            self.entry(parent)?;
+            if self.is_descendant(parent, node) {
+                return Err(FurikakeError::ParentCycle {
+                    name: "scene graph".to_string(),
+                });
+            }
+        }
+
+        self.detach(node);
+        if let Some(parent) = parent {
+            self.nodes[parent.slot as usize].children.push(node);
+        }
+
+        self.nodes[node.slot as usize].parent = parent;
+        self.mark_dirty(node);
+        Ok(())
+    }
+
+    pub fn contains(&self, node: Handle<SceneNode>) -> bool {
+        node.valid()
+            && self
+                .nodes
+                .get(node.slot as usize)
+                .is_some_and(|entry| entry.alive && entry.generation == node.generation)
+    }
+
+    pub fn parent(
+        &self,
+        node: Handle<SceneNode>,
+    ) -> Result<Option<Handle<SceneNode>>, FurikakeError> {
+        Ok(self.entry(node)?.parent)
+    }
+
+    pub fn children(&self, node: Handle<SceneNode>) -> Result<&[Handle<SceneNode>], FurikakeError> {
+        Ok(&self.entry(node)?.children)
+    }
+
+    pub fn local(&self, node: Handle<SceneNode>) -> Result<&LocalTransform, FurikakeError> {
+        Ok(&self.entry(node)?.local)
+    }
+
+    /// Mutable access to a node's local transform. Marks the node dirty; its
+    /// subtree is recomputed with it on the next [`SceneGraph::update`].
+    pub fn local_mut(
+        &mut self,
+        node: Handle<SceneNode>,
+    ) -> Result<&mut LocalTransform, FurikakeError> {
+        self.entry(node)?;
+        self.mark_dirty(node);
+        Ok(&mut self.nodes[node.slot as usize].local)
+    }
+
+    /// World matrix computed by the last [`SceneGraph::update`].
+    pub fn world(&self, node: Handle<SceneNode>) -> Result<Mat4, FurikakeError> {
+        Ok(self.entry(node)?.world)
+    }
+
+    pub fn transformation(
+        &self,
+        node: Handle<SceneNode>,
+    ) -> Result<Handle<Transformation>, FurikakeError> {
+        Ok(self.entry(node)?.transformation)
+    }
+
+    /// Recomputes world matrices for dirty subtrees and writes them into the
+    /// transformation pool. Returns the number of nodes written.
+    pub fn update(&mut self, transformations: &mut ReservedBindlessTransformations) -> usize {
+        let mut written = 0;
+        for root in std::mem::take(&mut self.dirty) {
+            // Subtrees under a dirty ancestor are written with that ancestor.
+            if !self.contains(root)
+                || !self.nodes[root.slot as usize].dirty
+                || self.has_dirty_ancestor(root)
+            {
+                continue;
+            }
+
+            let parent_world = self.nodes[root.slot as usize]
+                .parent
+                .map_or(Mat4::IDENTITY, |parent| {
+                    self.nodes[parent.slot as usize].world
+                });
+            let mut stack = vec![(root.slot as usize, parent_world)];
+            while let Some((index, parent_world)) = stack.pop() {
+                let node = &mut self.nodes[index];
+                node.world = parent_world * node.local.as_matrix();
+                node.dirty = false;
+                transformations
+                    .transformation_mut(node.transformation)
+                    .transform = node.world;
+                written += 1;
+
+                for child in &node.children {
+                    stack.push((child.slot as usize, node.world));
+                }
+            }
+        }
+
+        written
+    }
+
+    fn entry(&self, node: Handle<SceneNode>) -> Result<&SceneNode, FurikakeError> {
+        if !self.contains(node) {
+            return Err(stale_handle());
+        }
+        Ok(&self.nodes[node.slot as usize])
+    }
+
+    fn mark_dirty(&mut self, node: Handle<SceneNode>) {
+        let entry = &mut self.nodes[node.slot as usize];
+        if !entry.dirty {
+            entry.dirty = true;
+            self.dirty.push(node);
+        }
+    }
+
+    fn has_dirty_ancestor(&self, node: Handle<SceneNode>) -> bool {
+        let mut current = self.nodes[node.slot as usize].parent;
+        while let Some(handle) = current {
+            let entry = &self.nodes[handle.slot as usize];
+            if entry.dirty {
+                return true;
+            }
+            current = entry.parent;
+        }
+        false
+    }
+
+    fn detach(&mut self, node: Handle<SceneNode>) {
+        if let Some(parent) = self.nodes[node.slot as usize].parent.take() {
+            self.nodes[parent.slot as usize]
+                .children
+                .retain(|child| child.slot != node.slot);
+        }
+    }
+
+    fn is_descendant(&self, node: Handle<SceneNode>, ancestor: Handle<SceneNode>) -> bool {
+        let mut current = Some(node);
+        while let Some(handle) = current {
+            if handle.slot == ancestor.slot {
+                return true;
+            }
+            current = self.nodes[handle.slot as usize].parent;
+        }
+        false
+    }
+}
+
+fn stale_handle() -> FurikakeError {
+    FurikakeError::StaleHandle {
+        name: "scene graph".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dashi::{Context, ContextInfo};
+
+    #[test]
+    fn propagates_parent_transforms() {
+        let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
+        let mut transformations = ReservedBindlessTransformations::new(&mut ctx);
+        let mut scene = SceneGraph::new();
+
+        let parent = scene
+            .add_node(&mut transformations, None)
+            .expect("add node");
+        let child = scene
+            .add_node(&mut transformations, Some(parent))
+            .expect("add node");
+        *scene.local_mut(parent).expect("live node") =
+            LocalTransform::from_translation(Vec3::new(1.0, 0.0, 0.0));
+        *scene.local_mut(child).expect("live node") =
+            LocalTransform::from_translation(Vec3::new(0.0, 2.0, 0.0));
+
+        assert_eq!(scene.update(&mut transformations), 2);
+
+        let child_world = transformations
+            .transformation(scene.transformation(child).expect("live node"))
+            .transform;
+        assert_eq!(child_world.w_axis.truncate(), Vec3::new(1.0, 2.0, 0.0));
+    }
+
+    #[test]
+    fn only_updates_dirty_subtrees() {
+        let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
+        let mut transformations = ReservedBindlessTransformations::new(&mut ctx);
+        let mut scene = SceneGraph::new();
+
+        let root = scene
+            .add_node(&mut transformations, None)
+            .expect("add node");
+        let left = scene
+            .add_node(&mut transformations, Some(root))
+            .expect("add node");
+        let right = scene
+            .add_node(&mut transformations, Some(root))
+            .expect("add node");
+        let leaf = scene
+            .add_node(&mut transformations, Some(left))
+            .expect("add node");
+        assert_eq!(scene.update(&mut transformations), 4);
+        assert_eq!(scene.update(&mut transformations), 0);
+
+        scene.local_mut(right).expect("live node").translation = Vec3::X;
+        assert_eq!(scene.update(&mut transformations), 1);
+
+        scene.local_mut(left).expect("live node").translation = Vec3::Y;
+        assert_eq!(scene.update(&mut transformations), 2);
+        assert_eq!(
+            scene.world(leaf).expect("live node").w_axis.truncate(),
+            Vec3::Y
+        );
+
+        scene.local_mut(root).expect("live node").translation = Vec3::Z;
+        assert_eq!(scene.update(&mut transformations), 4);
+        assert_eq!(
+            scene.world(leaf).expect("live node").w_axis.truncate(),
+            Vec3::Y + Vec3::Z
+        );
+    }
+
+    #[test]
+    fn reparents_and_removes_subtrees() {
+        let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
+        let mut transformations = ReservedBindlessTransformations::new(&mut ctx);
+        let mut scene = SceneGraph::new();
+
+        let a = scene
+            .add_node(&mut transformations, None)
+            .expect("add node");
+        let b = scene
+            .add_node(&mut transformations, None)
+            .expect("add node");
+        let child = scene
+            .add_node(&mut transformations, Some(a))
+            .expect("add node");
+        scene.local_mut(b).expect("live node").translation = Vec3::X;
+
+        scene.set_parent(child, Some(b)).expect("reparent");
+        assert!(scene.children(a).expect("live node").is_empty());
+        assert_eq!(
+            scene.parent(child).expect("live node").map(|p| p.slot),
+            Some(b.slot)
+        );
+        assert!(matches!(
+            scene.set_parent(b, Some(child)),
+            Err(FurikakeError::ParentCycle { .. })
+        ));
+
+        scene.update(&mut transformations);
+        assert_eq!(
+            scene.world(child).expect("live node").w_axis.truncate(),
+            Vec3::X
+        );
+
+        scene.remove_node(&mut transformations, b);
+        assert!(!scene.contains(b));
+        assert!(!scene.contains(child));
+        assert!(scene.contains(a));
+    }
+
+    #[test]
+    fn rejects_stale_handles() {
+        let mut ctx = Context::headless(&ContextInfo::default()).expect("create context");
+        let mut transformations = ReservedBindlessTransformations::new(&mut ctx);
+        let mut scene = SceneGraph::new();
+
+        let removed = scene
+            .add_node(&mut transformations, None)
+            .expect("add node");
+        scene.remove_node(&mut transformations, removed);
+        let reused = scene
+            .add_node(&mut transformations, None)
+            .expect("add node");
+
+        assert_eq!(reused.slot, removed.slot);
+        assert!(scene.contains(reused));
+        assert!(!scene.contains(removed));
+        assert!(matches!(
+            scene.add_node(&mut transformations, Some(removed)),
+            Err(FurikakeError::StaleHandle { .. })
+        ));
+        assert!(matches!(
+            scene.add_node(&mut transformations, Some(Handle::new(42, 0))),
+            Err(FurikakeError::StaleHandle { .. })
+        ));
+        assert!(matches!(
+            scene.set_parent(reused, Some(removed)),
+            Err(FurikakeError::StaleHandle { .. })
+        ));
+        assert!(matches!(
+            scene.world(removed),
+            Err(FurikakeError::StaleHandle { .. })
+        ));
+    }
+}
@@ -1,4 +1,4 @@
-use glam::{Mat4, Quat, Vec3};
+use glam::{Mat3, Mat4, Quat, Vec2, Vec3, Vec4};
 
 #[repr(C)]
 #[derive(Clone, Copy)]
@@ -61,7 +61,28 @@ impl Camera {
 #[repr(C)]
 #[derive(Clone, Copy, Default)]
 pub struct Transformation {
+    /// Model matrix written by the application.
     pub transform: Mat4,
+    /// Inverse-transpose of `transform`. Stored as a `mat4` so the std430
+    /// layout matches without `mat3` column padding.
+    pub normal_matrix: Mat4,
+    /// `transform` as of the previous `update()`, for motion vectors.
+    pub previous_transform: Mat4,
+}
+
+impl Transformation {
+    /// Inverse-transpose of `transform`, or identity when it is singular.
+    pub fn compute_normal_matrix(&self) -> Mat4 {
+        // Compare against the column lengths so uniformly tiny scales are
+        // not mistaken for a collapsed axis.
+        let linear = Mat3::from_mat4(self.transform);
+        let scale = linear.x_axis.length() * linear.y_axis.length() * linear.z_axis.length();
+        if linear.determinant().abs() <= f32::EPSILON * scale {
+            return Mat4::IDENTITY;
+        }
+
+        self.transform.inverse().transpose()
+    }
 }
 
 #[repr(C)]
@@ -76,10 +97,469 @@ pub struct Texture {
 #[repr(C)]
 #[derive(Clone, Copy, Default)]
 pub struct Material {
-    pub base_color_texture_id: u16,
-    pub normal_texture_id: u16,
-    pub metallic_roughness_texture_id: u16,
-    pub occlusion_texture_id: u16,
-    pub emissive_texture_id: u16,
-    pub _padding: u16,
+    pub base_color_texture_id: u32,
+    pub normal_texture_id: u32,
+    pub metallic_roughness_texture_id: u32,
+    pub occlusion_texture_id: u32,
+    pub emissive_texture_id: u32,
+    pub _padding: u32,
+}
+
+/// Default vertex layout pulled from `meshi_bindless_vertices`. Texture
+/// coordinates are split across the padding of the `vec3` members.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Vertex {
+    pub position: Vec3,
+    pub u: f32,
+    pub normal: Vec3,
+    pub v: f32,
+    pub tangent: Vec4,
+}
+
+/// Location of a mesh inside the shared vertex and index buffers. Index values
+/// are relative to `vertex_offset`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Mesh {
+    pub vertex_offset: u32,
+    pub vertex_count: u32,
+    pub index_offset: u32,
+    pub index_count: u32,
+}
+
+/// Range of a skeleton's skin matrices in `meshi_bindless_joints`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Skeleton {
+    pub joint_offset: u32,
+    pub joint_count: u32,
+}
+
+/// `Instance::flags` bit for instances that are drawn.
+pub const INSTANCE_VISIBLE: u32 = 1;
+
+/// Per-instance record tying together the other bindless pools.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Instance {
+    pub transform_index: u32,
+    pub material_index: u32,
+    pub mesh_index: u32,
+    pub flags: u32,
+}
+
+impl Instance {
+    pub fn new<M>(
+        transformation: dashi::Handle<Transformation>,
+        material: dashi::Handle<M>,
+        mesh: dashi::Handle<Mesh>,
+    ) -> Self {
+        Self {
+            transform_index: u32::from(transformation.slot),
+            material_index: u32::from(material.slot),
+            mesh_index: u32::from(mesh.slot),
+            flags: INSTANCE_VISIBLE,
+        }
+    }
+}
+
+/// Matches `VkDrawIndexedIndirectCommand`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DrawIndexedIndirectCommand {
+    pub index_count: u32,
+    pub instance_count: u32,
+    pub first_index: u32,
+    pub vertex_offset: i32,
+    pub first_instance: u32,
+}
+
+/// Matches `VkDispatchIndirectCommand`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DispatchIndirectCommand {
+    pub x: u32,
+    pub y: u32,
+    pub z: u32,
+}
+
+/// Shadow map index for lights that do not cast shadows.
+pub const NO_SHADOW_MAP: u32 = u32::MAX;
+
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LightKind {
+    #[default]
+    Directional = 0,
+    Point = 1,
+    Spot = 2,
+    Area = 3,
+}
+
+/// Light record laid out to match the std430 `Light` struct in shaders.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Light {
+    pub position: Vec3,
+    /// A [`LightKind`] discriminant.
+    pub kind: u32,
+    pub direction: Vec3,
+    pub range: f32,
+    pub color: Vec3,
+    pub intensity: f32,
+    /// Spot light cone angles in radians.
+    pub inner_cone_angle: f32,
+    pub outer_cone_angle: f32,
+    pub shadow_map_index: u32,
+    pub _padding0: u32,
+    /// Area light width and height.
+    pub area_extent: Vec2,
+    pub _padding1: Vec2,
+}
+
+impl Default for Light {
+    fn default() -> Self {
+        Self {
+            position: Vec3::ZERO,
+            kind: LightKind::Directional as u32,
+            direction: Vec3::NEG_Z,
+            range: 0.0,
+            color: Vec3::ONE,
+            intensity: 1.0,
+            inner_cone_angle: 0.0,
+            outer_cone_angle: 0.0,
+            shadow_map_index: NO_SHADOW_MAP,
+            _padding0: 0,
+            area_extent: Vec2::ZERO,
+            _padding1: Vec2::ZERO,
+        }
+    }
+}
+
+impl Light {
+    pub fn directional(direction: Vec3, color: Vec3, intensity: f32) -> Self {
+        Self {
+            kind: LightKind::Directional as u32,
+            direction: direction.normalize(),
+            color,
+            intensity,
+            ..Default::default()
+        }
+    }
+
+    pub fn point(position: Vec3, color: Vec3, intensity: f32, range: f32) -> Self {
+        Self {
+            kind: LightKind::Point as u32,
+            position,
+            color,
+            intensity,
+            range,
+            ..Default::default()
+        }
+    }
+
+    pub fn spot(
+        position: Vec3,
+        direction: Vec3,
+        color: Vec3,
+        intensity: f32,
+        range: f32,
+        inner_cone_angle: f32,
+        outer_cone_angle: f32,
+    ) -> Self {
+        Self {
+            kind: LightKind::Spot as u32,
+            position,
+            direction: direction.normalize(),
+            color,
+            intensity,
+            range,
+            inner_cone_angle,
+            outer_cone_angle,
+            ..Default::default()
+        }
+    }
+
+    pub fn area(
+        position: Vec3,
+        direction: Vec3,
+        extent: Vec2,
+        color: Vec3,
+        intensity: f32,
+        range: f32,
+    ) -> Self {
+        Self {
+            kind: LightKind::Area as u32,
+            position,
+            direction: direction.normalize(),
+            area_extent: extent,
+            color,
+            intensity,
+            range,
+            ..Default::default()
+        }
+    }
+
+    pub fn light_kind(&self) -> LightKind {
+        match self.kind {
+            1 => LightKind::Point,
+            2 => LightKind::Spot,
+            3 => LightKind::Area,
+            _ => LightKind::Directional,
+        }
+    }
+}
+
+/// Sky and image-based-lighting parameters, laid out for a std140 uniform.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Environment {
+    /// Irradiance spherical harmonics, bands 0-2. `xyz` is RGB, `w` is unused.
+    pub irradiance_sh: [Vec4; 9],
+    /// Direction towards the sun in `xyz`, sun intensity in `w`.
+    pub sun_direction: Vec4,
+    /// Slot of the environment map in `meshi_bindless_textures`.
+    pub environment_texture_id: u32,
+    pub exposure: f32,
+    pub _padding: [f32; 2],
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self {
+            irradiance_sh: [Vec4::ZERO; 9],
+            sun_direction: Vec3::Y.extend(1.0),
+            environment_texture_id: u32::MAX,
+            exposure: 1.0,
+            _padding: [0.0; 2],
+        }
+    }
+}
+
+/// Per-viewport resolution data, laid out for a std140 uniform array.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Viewport {
+    /// Render resolution after dynamic-resolution scaling, in pixels.
+    pub resolution: Vec2,
+    pub inv_resolution: Vec2,
+    /// Size of the viewport on the output surface, in pixels.
+    pub output_resolution: Vec2,
+    /// Top-left corner of the viewport on the output surface, in pixels.
+    pub offset: Vec2,
+    pub render_scale: f32,
+    pub _padding: [f32; 3],
+}
+
+/// One simulated particle, std430 layout.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Particle {
+    pub position: Vec3,
+    pub age: f32,
+    pub velocity: Vec3,
+    pub lifetime: f32,
+    pub color: Vec4,
+    pub size: f32,
+    pub emitter_index: u32,
+    pub _padding: [u32; 2],
+}
+
+/// Spawn parameters for one emitter, std430 layout.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ParticleEmitter {
+    pub position: Vec3,
+    /// Particles spawned per second.
+    pub spawn_rate: f32,
+    pub direction: Vec3,
+    /// Half-angle of the spawn cone in radians.
+    pub spread: f32,
+    pub color: Vec4,
+    pub speed: f32,
+    pub lifetime: f32,
+    pub size: f32,
+    /// Particles to spawn this frame, filled in from `spawn_rate`.
+    pub spawn_count: u32,
+}
+
+/// `Bounds::flags` bit set once local bounds have been assigned.
+pub const BOUNDS_VALID: u32 = 1;
+
+/// Local and world-space bounds for one transformation slot, std430 layout.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Bounds {
+    pub local_center: Vec3,
+    pub local_radius: f32,
+    pub local_min: Vec3,
+    pub flags: u32,
+    pub local_max: Vec3,
+    pub _padding0: f32,
+    pub world_center: Vec3,
+    pub world_radius: f32,
+    pub world_min: Vec3,
+    pub _padding1: f32,
+    pub world_max: Vec3,
+    pub _padding2: f32,
+}
+
+impl Bounds {
+    /// Bounds with the sphere enclosing the box.
+    pub fn from_aabb(min: Vec3, max: Vec3) -> Self {
+        let center = (min + max) * 0.5;
+        Self::new(min, max, center, (max - center).length())
+    }
+
+    pub fn new(min: Vec3, max: Vec3, sphere_center: Vec3, sphere_radius: f32) -> Self {
+        let mut bounds = Self {
+            local_center: sphere_center,
+            local_radius: sphere_radius,
+            local_min: min,
+            local_max: max,
+            flags: BOUNDS_VALID,
+            ..Default::default()
+        };
+        bounds.transform(&Mat4::IDENTITY);
+        bounds
+    }
+
+    /// Recomputes the world-space box and sphere from the local ones.
+    pub fn transform(&mut self, transform: &Mat4) {
+        let center = (self.local_min + self.local_max) * 0.5;
+        let extent = (self.local_max - self.local_min) * 0.5;
+        let world_center = transform.transform_point3(center);
+        let world_extent = Vec3::new(
+            transform.row(0).truncate().abs().dot(extent),
+            transform.row(1).truncate().abs().dot(extent),
+            transform.row(2).truncate().abs().dot(extent),
+        );
+        self.world_min = world_center - world_extent;
+        self.world_max = world_center + world_extent;
+
+        let max_scale = transform
+            .x_axis
+            .truncate()
+            .length()
+            .max(transform.y_axis.truncate().length())
+            .max(transform.z_axis.truncate().length());
+        self.world_center = transform.transform_point3(self.local_center);
+        self.world_radius = self.local_radius * max_scale;
+    }
 }
+
+crate::shader_layout!(Camera { position, rotation });
+crate::shader_layout!(Transformation {
+    transform,
+    normal_matrix,
+    previous_transform
+});
+crate::shader_layout!(Texture {
+    id,
+    width,
+    height,
+    mip_levels
+});
+crate::shader_layout!(Material {
+    base_color_texture_id,
+    normal_texture_id,
+    metallic_roughness_texture_id,
+    occlusion_texture_id,
+    emissive_texture_id,
+    _padding
+});
+crate::shader_layout!(Vertex {
+    position,
+    u,
+    normal,
+    v,
+    tangent
+});
+crate::shader_layout!(Mesh {
+    vertex_offset,
+    vertex_count,
+    index_offset,
+    index_count
+});
+crate::shader_layout!(Skeleton {
+    joint_offset,
+    joint_count
+});
+crate::shader_layout!(Instance {
+    transform_index,
+    material_index,
+    mesh_index,
+    flags
+});
+crate::shader_layout!(DrawIndexedIndirectCommand {
+    index_count,
+    instance_count,
+    first_index,
+    vertex_offset,
+    first_instance
+});
+crate::shader_layout!(Light {
+    position,
+    kind,
+    direction,
+    range,
+    color,
+    intensity,
+    inner_cone_angle,
+    outer_cone_angle,
+    shadow_map_index,
+    _padding0,
+    area_extent,
+    _padding1
+});
+crate::shader_layout!(Environment {
+    irradiance_sh[Vec4],
+    sun_direction,
+    environment_texture_id,
+    exposure,
+    _padding
+});
+crate::shader_layout!(Viewport {
+    resolution,
+    inv_resolution,
+    output_resolution,
+    offset,
+    render_scale,
+    _padding
+});
+crate::shader_layout!(Particle {
+    position,
+    age,
+    velocity,
+    lifetime,
+    color,
+    size,
+    emitter_index,
+    _padding
+});
+crate::shader_layout!(ParticleEmitter {
+    position,
+    spawn_rate,
+    direction,
+    spread,
+    color,
+    speed,
+    lifetime,
+    size,
+    spawn_count
+});
+crate::shader_layout!(Bounds {
+    local_center,
+    local_radius,
+    local_min,
+    flags,
+    local_max,
+    _padding0,
+    world_center,
+    world_radius,
+    world_min,
+    _padding1,
+    world_max,
+    _padding2
+});